@@ -1,160 +1,604 @@
-use log::{error, info};
+use std::fs;
+use std::io::{BufRead, Write};
+use std::path::Path;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+use log::{debug, error, info, warn};
 use prettytable::{row, Table};
 use colored::Colorize;
-use crate::service::shelly_rest_api::Shelly;
+use crate::config::Config;
+use crate::credentials;
+use crate::log_file::{LogFile, LogFileFormat, LogRotation};
+use crate::logger::{LogFilter, Logger};
+use crate::output::{format_bytes, format_duration, format_eval_result, print_json, DeviceReport, EvalReport, OutputFormat, ScriptActionReport, ScriptEnableReport, ScriptListReport, ScriptReport, StatusReport};
+use crate::cli_error::CliError;
+use crate::service::shelly_rest_api::{Script, Shelly, ShellyError, SCRIPTING_LIMITS};
 use crate::service::vscode_tasks::SetupVsCode;
 
-///To create the configuration file under ./vscode
-pub fn setup(vscode: bool) {
-    if vscode {
-        let result = SetupVsCode::new();
-
-        if result.is_err() {
-            error!("Failed to create the config on initialisation");
-            error!("Due to -> {}", result.unwrap_err());
-            return;
-        }
+///The delay in milliseconds to wait for the logs printed by an evaluated expression before exiting
+const EVAL_LOG_DELAY: u64 = 500;
 
-        let setup_vs_code= result.as_ref().unwrap();
+///The maximum time in milliseconds to wait for the connection to the logs before evaluating an expression
+const EVAL_LOGGER_TIMEOUT: u64 = 3000;
 
-        let write_result = setup_vs_code.write();
+///The commands that end the REPL
+const REPL_EXIT_COMMANDS: [&str; 2] = ["exit", "quit"];
 
-        if write_result.is_err() {
-            error!("Failed to write the file");
-            error!("Due to -> {}", result.unwrap_err());
-            return;
-        } else {
-            info!("Config file created, you can now close this console");
-        }
-    } else {
-        error!("You have to choose a config profile to create the config !")
+///To create the configuration file under ./vscode
+///
+/// * `config` - The [Config] used by the configuration to communicate with the Shelly
+pub fn setup(config: &Config, vscode: bool) -> Result<(), CliError> {
+    if !vscode {
+        return Err(CliError::BadArgs("You have to choose a config profile to create the config !".to_string()));
     }
+
+    let setup_vs_code = SetupVsCode::new(config);
+
+    if let Err(error) = setup_vs_code.write() {
+        error!("Failed to write the file");
+        return Err(error.into());
+    }
+
+    info!("Config file created, you can now close this console");
+    Ok(())
+}
+
+///Ask the password of the Shelly and save it in the credential file
+///
+/// * `config` - The [Config] of the Shelly
+pub fn login(config: &Config) -> Result<(), CliError> {
+    let password = credentials::prompt_password(&config.host).inspect_err(|_| error!("Unable to read the password"))?;
+    let path = credentials::save_password(&config.host, &password).inspect_err(|_| error!("Unable to save the password"))?;
+
+    info!("The password of {} has been saved in {}", config.host, path.display());
+    Ok(())
+}
+
+///Print the logs of the Shelly, without checking or uploading the files
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `log_filter` - The [LogFilter] selecting the logs that are printed
+/// * `log_file` - The [LogFile] where the printed logs are also saved
+/// * `follow` - If [true] the logs are printed until a limit is reached, reconnecting when the connection is lost
+/// * `lines` - The number of logs printed before exiting, if [None] there is no limit
+/// * `duration` - The time in seconds before exiting, if [None] there is no limit
+pub fn logs(config: &Config, log_filter: LogFilter, log_file: Option<LogFile>, follow: bool, lines: Option<usize>, duration: Option<u64>) -> Result<(), CliError> {
+    let logger = Logger::new(config)
+        .with_filter(log_filter)
+        .with_log_file(log_file)
+        .with_limits(follow, lines);
+
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(logger.start());
+    });
+
+    let result = match duration {
+        None => receiver.recv().unwrap_or(Ok(())),
+        Some(duration) => match receiver.recv_timeout(Duration::from_secs(duration)) {
+            Ok(result) => result,
+            Err(RecvTimeoutError::Timeout) => {
+                debug!("{}s elapsed, stopping the logs", duration);
+                Ok(())
+            }
+            Err(RecvTimeoutError::Disconnected) => Ok(()),
+        },
+    };
+
+    result.inspect_err(|_| error!("Unable to listen the logs of the Shelly {}", config.host))?;
+    Ok(())
+}
+
+///Create the filter of the logs of the Shelly
+///
+/// * `config` - The [Config] used to communicate with the Shelly, to find the id of the script
+/// * `script_name` - The name of the script whose logs are printed, if [None] the logs of every script are printed
+/// * `grep` - The regex that the logs have to match
+/// * `exclude` - The regex that the logs must not match
+///
+///Return an Error if the script doesn't exist or if a regex is not valid
+pub fn log_filter(config: &Config, script_name: Option<&str>, grep: Option<&str>, exclude: Option<&str>) -> Result<LogFilter, CliError> {
+    let script = match script_name {
+        Some(script_name) => Some(find_script(&Shelly::new(config), script_name)?),
+        None => None,
+    };
+
+    LogFilter::new(script.map(|script| script.id), grep, exclude)
+        .map_err(|error| CliError::BadArgs(format!("The regex of the log filter is not valid -> {}", error)))
+}
+
+///Open the file where the logs of the Shelly are saved
+///
+/// * `path` - The path of the log file, if [None] the logs are not saved
+/// * `format` - How the logs are written in the file
+/// * `rotation` - When the file is rotated
+/// * `max_size` - The maximum size in MB of the file with [LogRotation::Size]
+/// * `keep` - How many rotated files are kept
+///
+///Return an Error if the file can't be opened
+pub fn log_file(path: Option<&str>, format: LogFileFormat, rotation: LogRotation, max_size: u64, keep: usize) -> Result<Option<LogFile>, CliError> {
+    let Some(path) = path else {
+        return Ok(None);
+    };
+
+    let log_file = LogFile::open(path, format, rotation, max_size.saturating_mul(1024 * 1024), keep)
+        .inspect_err(|_| error!("Unable to open the log file {}", path))?;
+
+    info!("The logs of the Shelly are saved in {}", path);
+    Ok(Some(log_file))
+}
+
+///Find a script by its name on the Shelly
+///
+/// * `shelly` - The [Shelly] where the script is
+/// * `script_name` - The name of the script
+///
+///Return [ShellyError::ScriptNotFound] if there is no script with this name
+fn find_script(shelly: &Shelly, script_name: &str) -> Result<Script, CliError> {
+    let script_list = shelly.script_list().inspect_err(|_| error!("Failed to get the script list from the Shelly"))?;
+
+    script_list
+        .into_iter()
+        .find(|script| script.name == script_name)
+        .ok_or_else(|| ShellyError::ScriptNotFound(script_name.to_string()).into())
 }
 
 ///Start script by it's name on the Shelly
-pub fn start(script_name: &str) {
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script to start
+/// * `output` - The [OutputFormat] of the result
+pub fn start(config: &Config, script_name: &str, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(&Config {
+        autorun: true,
+        ..config.clone()
+    });
 
-    std::env::set_var("shelly-autorun", true.to_string());
+    let script = find_script(&shelly, script_name)?;
+    shelly.script_start(&script).inspect_err(|_| error!("Unable to start script"))?;
 
-    let result = Shelly::new();
-    if result.is_err() {
-        error!("{:?}", result.unwrap_err());
-        return;
+    info!("script started !");
+    print_action_report(config, "start", &script, true, output)
+}
+
+///Stop script by it's name on the Shelly
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script to stop
+/// * `output` - The [OutputFormat] of the result
+pub fn stop(config: &Config, script_name: &str, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
+
+    let script = find_script(&shelly, script_name)?;
+    shelly.script_stop(&script).inspect_err(|_| error!("Unable to stop script"))?;
+
+    info!("script has been stopped !");
+    print_action_report(config, "stop", &script, false, output)
+}
+
+///Enable or disable script by it's name on the Shelly, an enabled script is run on the boot of the Shelly
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script to configure
+/// * `enable` - If [true] the script is enabled, if [false] it is disabled
+/// * `output` - The [OutputFormat] of the result
+pub fn set_enable(config: &Config, script_name: &str, enable: bool, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
+
+    let script = find_script(&shelly, script_name)?;
+    shelly.script_set_enable(&script, enable).inspect_err(|_| error!("Unable to configure script"))?;
+
+    info!("script has been {} !", if enable { "enabled" } else { "disabled" });
+
+    match output {
+        OutputFormat::Table => Ok(()),
+        OutputFormat::Plain => {
+            println!("{}\t{}\t{}", if enable { "enable" } else { "disable" }, script.id, script.name);
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&ScriptEnableReport {
+            device: DeviceReport::new(config),
+            id: script.id,
+            name: &script.name,
+            enable,
+        }),
     }
+}
 
-    let shelly = result.unwrap();
+///Evaluate an expression in a running script on the Shelly, the logs printed by the expression are shown too
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script where the expression is evaluated
+/// * `expression` - The JavaScript expression to evaluate
+/// * `output` - The [OutputFormat] of the result
+pub fn eval(config: &Config, script_name: &str, expression: &str, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
 
-    let list_result = shelly.script_list();
-    if list_result.is_err() {
-        error!("Failed to get the script list from the Shelly");
-        return;
+    let script = find_running_script(&shelly, script_name)?;
+    spawn_eval_logger(config, &script);
+
+    let result = shelly.script_eval(&script, expression).inspect_err(|_| error!("Unable to evaluate the expression"))?;
+
+    //give the time to the logs of the expression to be received
+    thread::sleep(Duration::from_millis(EVAL_LOG_DELAY));
+
+    match output {
+        OutputFormat::Table | OutputFormat::Plain => {
+            println!("{}", format_eval_result(&result));
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&EvalReport {
+            device: DeviceReport::new(config),
+            id: script.id,
+            name: &script.name,
+            expression,
+            result: serde_json::from_str(&result).unwrap_or(serde_json::Value::String(result)),
+        }),
     }
+}
 
-    let script_list = list_result.unwrap();
-    let script = script_list
-        .iter()
-        .filter_map(|script| {
-            if script.name == script_name {
-                Some(script)
-            } else {
-                None
-            }
-        }).next();
+///Start an interactive session where each line is evaluated in a running script on the Shelly
+///
+/// The session ends with `exit`, `quit` or the end of the input (``CTRL`` + ``D``), the errors of the
+/// expressions are printed without ending it
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script where the expressions are evaluated
+pub fn repl(config: &Config, script_name: &str) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
 
-    if script.is_none() {
-        error!("can't start the script, because is not existent");
-        return;
+    let script = find_running_script(&shelly, script_name)?;
+    spawn_eval_logger(config, &script);
+
+    info!("Connected to the script {}, type 'exit' to quit", script.name);
+
+    let mut lines = std::io::stdin().lock().lines();
+    loop {
+        print!("{}> ", script.name);
+        std::io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        let line = line?;
+        let expression = line.trim();
+
+        if expression.is_empty() {
+            continue;
+        }
+        if REPL_EXIT_COMMANDS.contains(&expression) {
+            break;
+        }
+
+        match shelly.script_eval(&script, expression) {
+            Ok(result) => println!("{}", format_eval_result(&result)),
+            Err(error) => error!("{}", error),
+        }
     }
 
-    let start_result = shelly.script_start(script.unwrap());
+    Ok(())
+}
 
-    if start_result.is_ok() {
-        info!("script started !");
-    } else {
-        error!("Unable to start script");
-        error!("Due to -> {}", start_result.unwrap_err())
+///Start listening the logs of the Shelly before evaluating an expression, like this the logs printed by the
+///expression are not lost while the websocket is connecting
+///
+/// Only the logs of the evaluated script are printed
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script` - The script where the expressions are evaluated
+fn spawn_eval_logger(config: &Config, script: &Script) {
+    //the regex built from the id of a script is always valid
+    let filter = LogFilter::new(Some(script.id), None, None).unwrap_or_default();
+
+    if !Logger::new(config).with_filter(filter).spawn_connected(Duration::from_millis(EVAL_LOGGER_TIMEOUT)) {
+        warn!("Not connected to the logs of the Shelly {} yet, the logs printed by the expression may be lost", config.host);
     }
 }
 
-///Stop script by it's name on the Shelly
-pub fn stop(script_name: &str) {
-    let result = Shelly::new();
-    if result.is_err() {
-        error!("{:?}", result.unwrap_err());
-        return;
+///Find a script by its name on the Shelly, with a warning if it isn't running
+///
+/// * `shelly` - The [Shelly] where the script is
+/// * `script_name` - The name of the script
+fn find_running_script(shelly: &Shelly, script_name: &str) -> Result<Script, CliError> {
+    let script = find_script(shelly, script_name)?;
+
+    if script.running == Some(false) {
+        warn!("The script {} is not running, the Shelly can only evaluate expressions in a running script", script.name);
     }
 
-    let shelly = result.unwrap();
+    Ok(script)
+}
 
-    let list_result = shelly.script_list();
-    if list_result.is_err() {
-        error!("Failed to get the script list from the Shelly");
-        return;
+///Print the result of an action done on a script, the [OutputFormat::Table] is already covered by the logs
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `action` - The name of the action, `start` or `stop`
+/// * `script` - The [Script] targeted by the action
+/// * `running` - If the script is running after the action
+/// * `output` - The [OutputFormat] of the result
+fn print_action_report(config: &Config, action: &'static str, script: &Script, running: bool, output: OutputFormat) -> Result<(), CliError> {
+    match output {
+        OutputFormat::Table => Ok(()),
+        OutputFormat::Plain => {
+            println!("{}\t{}\t{}", action, script.id, script.name);
+            Ok(())
+        }
+        OutputFormat::Json => print_json(&ScriptActionReport {
+            device: DeviceReport::new(config),
+            action,
+            id: script.id,
+            name: &script.name,
+            running,
+        }),
     }
+}
 
-    let script_list = list_result.unwrap();
-    let script = script_list
-        .iter()
-        .filter_map(|script| {
-            if script.name == script_name {
-                Some(script)
-            } else {
-                None
-            }
-        }).next();
+///Print the list of all scipts on the Shelly with their current status, the memory they use and their last errors
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `output` - The [OutputFormat] of the list
+pub fn list(config: &Config, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
 
-    if script.is_none() {
-        error!("can't stop the script, because is not existent");
-        return;
+    let script_list = shelly.script_list().inspect_err(|_| error!("Failed to get the script list from the Shelly"))?;
+    let scripts = script_reports(&shelly, script_list);
+
+    match output {
+        OutputFormat::Json => print_json(&ScriptListReport {
+            device: DeviceReport::new(config),
+            scripts: &scripts,
+        }),
+        OutputFormat::Table | OutputFormat::Plain => {
+            print_script_reports(&scripts, output);
+            Ok(())
+        }
     }
+}
 
-    let stop_result = shelly.script_stop(script.unwrap());
+///Print the identity and the status of the Shelly, with the memory used by each script
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `output` - The [OutputFormat] of the status
+pub fn status(config: &Config, output: OutputFormat) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
 
-    if stop_result.is_ok() {
-        info!("script has been stopped !")
+    let info = shelly.device_info().inspect_err(|_| error!("Failed to get the device info from the Shelly"))?;
+    let system = shelly.sys_status().inspect_err(|_| error!("Failed to get the system status from the Shelly"))?;
+    let scripting = shelly.supports_scripting();
+    let scripts = if scripting {
+        let script_list = shelly.script_list().inspect_err(|_| error!("Failed to get the script list from the Shelly"))?;
+        script_reports(&shelly, script_list)
     } else {
-        error!("Unable to stop script");
-        error!("Due to -> {}", stop_result.unwrap_err());
+        warn!("The Shelly is a Gen{} device, the scripting is not supported", shelly.generation());
+        Vec::new()
+    };
+
+    let mut properties = vec![
+        ("Name", info.name.clone().unwrap_or_default()),
+        ("Model", format!("{} ({})", info.model, info.app)),
+        ("Generation", info.generation.to_string()),
+        ("Firmware", format!("{} ({})", info.ver, info.fw_id)),
+        ("MAC", info.mac.clone()),
+        ("Authentication", info.auth_en.to_string()),
+        ("Uptime", format_duration(system.uptime)),
+        ("RAM free", format!("{} / {}", format_bytes(system.ram_free), format_bytes(system.ram_size))),
+        ("FS free", format!("{} / {}", format_bytes(system.fs_free), format_bytes(system.fs_size))),
+        ("Restart required", system.restart_required.to_string()),
+    ];
+    if scripting {
+        properties.extend([
+            ("Scripts", format!("{} / {}", scripts.len(), SCRIPTING_LIMITS.scripts)),
+            ("Timers per script", SCRIPTING_LIMITS.timers.to_string()),
+            ("Subscriptions per script", SCRIPTING_LIMITS.subscriptions.to_string()),
+            ("RPC calls per script", SCRIPTING_LIMITS.rpc_calls.to_string()),
+        ]);
     }
-}
 
-///Print the list of all scipts on the Shelly with their current status
-pub fn list() {
-    let result = Shelly::new();
-    if result.is_err() {
-        error!("{:?}", result.unwrap_err());
-        return;
+    match output {
+        OutputFormat::Json => print_json(&StatusReport {
+            device: DeviceReport::new(config),
+            info: &info,
+            system: &system,
+            limits: scripting.then_some(&SCRIPTING_LIMITS),
+            scripts: &scripts,
+        }),
+        OutputFormat::Plain => {
+            properties.iter().for_each(|(name, value)| println!("{}\t{}", name, value));
+            print_script_reports(&scripts, output);
+            Ok(())
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table.add_row(row!["Property".blue(), "Value".blue()]);
+            properties.iter().for_each(|(name, value)| {
+                table.add_row(row![name, value]);
+            });
+            table.printstd();
+
+            if scripting {
+                print_script_reports(&scripts, output);
+            }
+            Ok(())
+        }
     }
+}
+
+///Read the status of each script, a script whose status can't be read is reported without it
+///
+/// * `shelly` - The [Shelly] where the scripts are
+/// * `script_list` - The [Script] of the Shelly
+fn script_reports(shelly: &Shelly, script_list: Vec<Script>) -> Vec<ScriptReport> {
+    script_list
+        .into_iter()
+        .map(|script| {
+            let status = shelly
+                .script_status(&script)
+                .inspect_err(|error| warn!("Unable to get the status of the script {} -> {}", script.name, error))
+                .ok();
 
-    let shelly = result.unwrap();
+            ScriptReport::new(script, status)
+        })
+        .collect()
+}
+
+///Print the scripts with their status in a table or in lines, the missing values are printed as `-`
+///
+/// * `scripts` - The [ScriptReport] of each script
+/// * `output` - [OutputFormat::Table] or [OutputFormat::Plain]
+fn print_script_reports(scripts: &[ScriptReport], output: OutputFormat) {
+    let memory = |bytes: Option<u64>| bytes.map_or_else(|| "-".to_string(), format_bytes);
 
-    let list_result = shelly.script_list();
-    if list_result.is_err() {
-        error!("Failed to get the script list from the Shelly");
+    if output == OutputFormat::Plain {
+        scripts.iter().for_each(|script| {
+            let enable = match script.enable {
+                Some(true) => "Enable",
+                Some(false) => "Disable",
+                None => "-",
+            };
+            let running = match script.running {
+                Some(true) => "Running",
+                Some(false) => "Idle",
+                None => "-",
+            };
+            let errors = if script.errors.is_empty() { "-".to_string() } else { script.errors.join(",") };
+
+            println!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                script.id,
+                script.name,
+                enable,
+                running,
+                script.mem_used.map_or_else(|| "-".to_string(), |bytes| bytes.to_string()),
+                script.mem_peak.map_or_else(|| "-".to_string(), |bytes| bytes.to_string()),
+                script.mem_free.map_or_else(|| "-".to_string(), |bytes| bytes.to_string()),
+                errors,
+            );
+        });
         return;
     }
 
-    let script_list = list_result.unwrap();
-
     let mut table = Table::new();
-    table.add_row(row!["Id".blue(), "Name".blue(), "Is enable".blue(), "Is running".blue()]);
+    table.add_row(row![
+        "Id".blue(),
+        "Name".blue(),
+        "Is enable".blue(),
+        "Is running".blue(),
+        "Mem used".blue(),
+        "Mem peak".blue(),
+        "Mem free".blue(),
+        "Errors".blue(),
+    ]);
 
-    script_list.iter().for_each(|script| {
-        let id = script.id.to_string();
-        let name = &script.name;
-        let enable = if script.enable.unwrap() {
-            "Enable".green()
-        } else {
-            "Disable".red()
+    scripts.iter().for_each(|script| {
+        let enable = match script.enable {
+            Some(true) => "Enable".green(),
+            Some(false) => "Disable".red(),
+            None => "-".normal(),
+        };
+        let running = match script.running {
+            Some(true) => "Running".green(),
+            Some(false) => "Idle".red(),
+            None => "-".normal(),
         };
-        let running = if script.running.unwrap() {
-            "Running".green()
+        let errors = if script.errors.is_empty() {
+            "-".normal()
         } else {
-            "Idle".red()
+            script.errors.join(", ").red()
         };
 
-        table.add_row(row![id, name, enable, running]);
+        table.add_row(row![
+            script.id.to_string(),
+            script.name,
+            enable,
+            running,
+            memory(script.mem_used),
+            memory(script.mem_peak),
+            memory(script.mem_free),
+            errors,
+        ]);
     });
 
     table.printstd();
-}
\ No newline at end of file
+}
+
+///Download the scripts from the Shelly into `<name>.js` files
+///
+/// * `config` - The [Config] used to communicate with the Shelly
+/// * `script_name` - The name of the script to download, if [None] all the scripts are downloaded
+/// * `path` - The directory where the files will be written
+/// * `force` - If [true] the existing files are overwritten, if [false] they are skipped
+///
+///Every script is pulled even if one fails, then the last error is returned
+pub fn pull(config: &Config, script_name: Option<&str>, path: &str, force: bool) -> Result<(), CliError> {
+    let shelly = Shelly::new(config);
+
+    let scripts: Vec<Script> = match script_name {
+        Some(script_name) => vec![find_script(&shelly, script_name)?],
+        None => shelly.script_list().inspect_err(|_| error!("Failed to get the script list from the Shelly"))?,
+    };
+
+    fs::create_dir_all(path).inspect_err(|_| error!("Unable to create the directory {}", path))?;
+
+    let mut result = Ok(());
+    for script in scripts {
+        let file_path = match script_file_name(&script.name) {
+            Ok(file_name) => Path::new(path).join(file_name),
+            Err(error) => {
+                error!("Unable to pull the script {}", script.name);
+                error!("Due to -> {}", error);
+                result = Err(error.into());
+                continue;
+            }
+        };
+
+        if file_path.exists() && !force {
+            warn!("{} already exists, the script {} is not pulled (use --force to overwrite it)", file_path.display(), script.name);
+            continue;
+        }
+
+        let code = match shelly.script_get_code(&script) {
+            Ok(code) => code,
+            Err(error) => {
+                error!("Unable to download the script {}", script.name);
+                error!("Due to -> {}", error);
+                result = Err(error.into());
+                continue;
+            }
+        };
+
+        match fs::write(&file_path, code) {
+            Ok(_) => info!("{} has been pulled into {}", script.name, file_path.display()),
+            Err(error) => {
+                error!("Unable to write the file {}", file_path.display());
+                error!("Due to -> {}", error);
+                result = Err(error.into());
+            }
+        }
+    }
+
+    result
+}
+
+///Get the name of the file of a script pulled from the Shelly
+///
+/// The name comes from the Shelly, a name like `../x` or `/x` would write the file outside of the directory
+///
+/// * `script_name` - The name of the script on the Shelly
+///
+///Return [ShellyError::InvalidScriptName] if the name can't be used as a file name
+fn script_file_name(script_name: &str) -> Result<String, ShellyError> {
+    let reason = if script_name.is_empty() || script_name == "." || script_name == ".." {
+        format!("the name '{}' is reserved", script_name)
+    } else if let Some(separator) = script_name.chars().find(|c| *c == '/' || *c == '\\') {
+        format!("the name contains the path separator '{}'", separator)
+    } else {
+        return Ok(format!("{}.js", script_name));
+    };
+
+    Err(ShellyError::InvalidScriptName {
+        name: script_name.to_string(),
+        reason: format!("{}, it can't be used as a file name", reason),
+    })
+}
@@ -0,0 +1,86 @@
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::process::ExitCode;
+use crate::service::shelly_rest_api::ShellyError;
+
+///The exit codes of the utilitary, displayed at the end of the help
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  Success
+  1  Unexpected error (file system, ...)
+  2  Bad arguments or configuration
+  3  Authentication refused by the Shelly
+  4  Shelly unreachable (wrong host, network or timeout)
+  5  Script not found on the Shelly
+  6  Error returned by the Shelly, or scripting not supported (Gen1)";
+
+///The errors that make a command fail, each kind of error has its own exit code
+#[derive(Debug)]
+pub enum CliError {
+    ///The arguments or the configuration are not valid
+    BadArgs(String),
+    ///Something goes wrong when communicating with the Shelly
+    Shelly(ShellyError),
+    ///Any other error, like an error of the file system
+    Other(Box<dyn Error>),
+}
+
+impl CliError {
+    ///Get the exit code of the utilitary matching the error, see [EXIT_CODES_HELP]
+    pub fn exit_code(&self) -> ExitCode {
+        let code = match self {
+            CliError::Other(_) => 1,
+            CliError::BadArgs(_) | CliError::Shelly(ShellyError::InvalidScriptName { .. } | ShellyError::MissingPassword(_)) => 2,
+            CliError::Shelly(ShellyError::Unauthorized) => 3,
+            CliError::Shelly(ShellyError::Transport(_) | ShellyError::Timeout) => 4,
+            CliError::Shelly(ShellyError::ScriptNotFound(_)) => 5,
+            CliError::Shelly(ShellyError::NotFound(_) | ShellyError::UnsupportedGeneration(_) | ShellyError::Rpc { .. } | ShellyError::Parse(_)) => 6,
+        };
+
+        ExitCode::from(code)
+    }
+
+    ///Get a hint on how to solve the error, when there is one
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            CliError::Shelly(ShellyError::Unauthorized) => Some("Please check the username and the password (--password, SHELLY_PASSWORD or the 'login' command)"),
+            CliError::Shelly(ShellyError::Transport(_) | ShellyError::Timeout) => Some("Please check the host and that the Shelly is connected to the network"),
+            CliError::Shelly(ShellyError::InvalidScriptName { .. }) => Some("Please rename the script or the file, the name of an uploaded file can be changed with the --naming and --name-separator parameters"),
+            CliError::Shelly(ShellyError::UnsupportedGeneration(_)) => Some("Only the 'status' command can be used with this Shelly"),
+            CliError::Shelly(ShellyError::NotFound(_)) => Some("Please check that the Shelly is a Gen2 device or newer, the scripts are not available on the older ones"),
+            _ => None,
+        }
+    }
+}
+
+impl Error for CliError {}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::BadArgs(message) => write!(f, "{}", message),
+            CliError::Shelly(error) => write!(f, "{}", error),
+            CliError::Other(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl From<ShellyError> for CliError {
+    fn from(error: ShellyError) -> Self {
+        CliError::Shelly(error)
+    }
+}
+
+impl From<std::io::Error> for CliError {
+    fn from(error: std::io::Error) -> Self {
+        CliError::Other(Box::new(error))
+    }
+}
+
+impl From<Box<dyn Error>> for CliError {
+    fn from(error: Box<dyn Error>) -> Self {
+        match error.downcast::<ShellyError>() {
+            Ok(error) => CliError::Shelly(*error),
+            Err(error) => CliError::Other(error),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::read_to_string;
+use std::path::Path;
+use serde::Deserialize;
+use crate::logger::DeviceLogLevel;
+use crate::service::shelly_rest_api::DEFAULT_CHUNK_SIZE;
+
+///The project config file, read in the current directory if no other file is indicated
+pub const CONFIG_FILE: &str = "shelly.toml";
+
+///The default port of the HTTP API and of the websocket of the Shelly
+pub const DEFAULT_PORT: u16 = 80;
+
+///The default username of the account on the Shelly
+pub const DEFAULT_USERNAME: &str = "admin";
+
+///The default directory where the scripts are
+pub const DEFAULT_SCRIPTS_DIR: &str = "./";
+
+///Representation of the project config file, with the device profiles
+///
+/// ```toml
+/// default_profile = "kitchen"
+///
+/// [profiles.kitchen]
+/// host = "192.168.1.20"
+/// scripts_dir = "./scripts"
+/// autorun = true
+/// enable = true
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ConfigFile {
+    ///The profile used when no profile is indicated in the command line
+    default_profile: Option<String>,
+    ///The device profiles by their name
+    #[serde(default)]
+    profiles: HashMap<String, Profile>,
+}
+
+///A device profile of the project config file, each value can be overridden in the command line
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Profile {
+    ///The name of the profile, [None] when no profile is used
+    #[serde(skip)]
+    pub name: Option<String>,
+    ///The IP of the Shelly
+    pub host: Option<String>,
+    ///The port of the HTTP API of the Shelly
+    pub port: Option<u16>,
+    ///The username of the account on the Shelly
+    pub username: Option<String>,
+    ///The directory where the scripts are
+    pub scripts_dir: Option<String>,
+    ///If the scripts are directly run when uploaded to the Shelly
+    pub autorun: Option<bool>,
+    ///If the uploaded scripts are enabled to run on the boot of the Shelly
+    pub enable: Option<bool>,
+    ///The port of the websocket used to get the logs on the Shelly
+    pub ws_port: Option<u16>,
+}
+
+impl ConfigFile {
+    ///Read the project config file
+    ///
+    /// * `path` - The path of the config file, if [None] the [CONFIG_FILE] of the current directory is read when it exists
+    ///
+    ///Return an Error if the file can't be read or parsed
+    pub fn load(path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let path = match path {
+            Some(path) => path,
+            None if Path::new(CONFIG_FILE).is_file() => CONFIG_FILE,
+            None => return Ok(ConfigFile::default()),
+        };
+
+        let content = read_to_string(path)
+            .map_err(|error| format!("Unable to read the config file {} -> {}", path, error))?;
+
+        Ok(toml::from_str(&content)
+            .map_err(|error| format!("Unable to parse the config file {} -> {}", path, error))?)
+    }
+
+    ///Get a profile by its name
+    ///
+    /// * `name` - The name of the profile, if [None] the default profile is used when there is one
+    ///
+    ///Return an Error if the profile doesn't exist
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, Box<dyn Error>> {
+        match name.or(self.default_profile.as_deref()) {
+            None => Ok(Profile::default()),
+            Some(name) => self.profiles
+                .get(name)
+                .cloned()
+                .map(|profile| Profile { name: Some(name.to_string()), ..profile })
+                .ok_or_else(|| format!("The profile {} doesn't exist in the config file", name).into()),
+        }
+    }
+}
+
+///The configuration used to communicate with the Shelly, built from the profile and the command line
+#[derive(Clone)]
+pub struct Config {
+    ///The config file indicated in the command line, [None] if the default one is used
+    pub config_file: Option<String>,
+    ///The name of the profile used, [None] when no profile is used
+    pub profile: Option<String>,
+    ///The IP of the Shelly
+    pub host: String,
+    ///The port of the HTTP API of the Shelly
+    pub port: u16,
+    ///The username of the account on the Shelly
+    pub username: String,
+    ///The password indicated in the command line, when [None] it is found with [crate::credentials::find_password] if the Shelly requires it
+    pub password: Option<String>,
+    ///The directory where the scripts are
+    pub scripts_dir: String,
+    ///If the scripts are directly run when uploaded to the Shelly
+    pub autorun: bool,
+    ///If the uploaded scripts are enabled to run on the boot of the Shelly
+    pub enable: bool,
+    ///The port of the websocket used to get the logs on the Shelly
+    pub ws_port: u16,
+    ///The maximum size in bytes of each part of the code uploaded to the Shelly
+    pub chunk_size: usize,
+    ///The least important level of the logs of the Shelly that are printed
+    pub device_log_level: DeviceLogLevel,
+}
+
+impl Config {
+    ///Create the configuration from a profile, the values indicated in the command line override the ones of the profile
+    ///
+    /// * `profile` - The profile of the config file
+    /// * `host` - The IP of the Shelly indicated in the command line
+    /// * `port` - The port of the HTTP API indicated in the command line
+    /// * `username` - The username indicated in the command line
+    ///
+    ///Return an Error if the host is neither in the command line nor in the profile
+    pub fn new(profile: Profile, host: Option<String>, port: Option<u16>, username: Option<String>) -> Result<Self, Box<dyn Error>> {
+        Ok(Config {
+            config_file: None,
+            profile: profile.name,
+            host: host
+                .or(profile.host)
+                .ok_or("The host of the Shelly must be indicated with --host or in the profile")?,
+            port: port.or(profile.port).unwrap_or(DEFAULT_PORT),
+            username: username
+                .or(profile.username)
+                .unwrap_or_else(|| DEFAULT_USERNAME.to_string()),
+            password: None,
+            scripts_dir: profile.scripts_dir.unwrap_or_else(|| DEFAULT_SCRIPTS_DIR.to_string()),
+            autorun: profile.autorun.unwrap_or(false),
+            enable: profile.enable.unwrap_or(false),
+            ws_port: profile.ws_port.unwrap_or(DEFAULT_PORT),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            device_log_level: DeviceLogLevel::Info,
+        })
+    }
+}
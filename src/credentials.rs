@@ -0,0 +1,218 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs::{self, OpenOptions};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use log::debug;
+
+///The environment variable containing the password of the Shelly
+pub const PASSWORD_ENV_VAR: &str = "SHELLY_PASSWORD";
+
+///The name of the directory of the utilitary in the user config directory
+const CONFIG_DIR_NAME: &str = "shelly_remote_helper";
+
+///The name of the credential file, containing the password of each host
+const CREDENTIAL_FILE: &str = "credentials.toml";
+
+///What replace the password in the log output
+const REDACTED: &str = "********";
+
+///The secrets shorter than this are not removed from the log output, they would be found in too many words
+const MIN_SECRET_LENGTH: usize = 4;
+
+///The password currently used, to be able to remove it from the log output
+static SECRET: OnceLock<String> = OnceLock::new();
+
+///Find the password of the Shelly, in this order :
+///
+/// 1. the password indicated in the command line
+/// 2. the [PASSWORD_ENV_VAR] environment variable
+/// 3. the credential file in the user config directory
+/// 4. a prompt, if the utilitary is run in a terminal
+///
+/// * `password` - The password indicated in the command line
+/// * `host` - The IP of the Shelly, used to find its password in the credential file
+///
+///Return an Error if no password has been found
+pub fn find_password(password: Option<String>, host: &str) -> Result<String, Box<dyn Error>> {
+    let password = match password {
+        Some(password) => {
+            debug!("Password read from the command line");
+            password
+        }
+        None => match std::env::var(PASSWORD_ENV_VAR) {
+            Ok(password) => {
+                debug!("Password read from the {} environment variable", PASSWORD_ENV_VAR);
+                password
+            }
+            Err(_) => match read_credential_file()?.remove(host) {
+                Some(password) => {
+                    debug!("Password read from the credential file");
+                    password
+                }
+                None if std::io::stdin().is_terminal() => prompt_password(host)?,
+                None => return Err(format!(
+                    "No password found for {}, set the {} environment variable or save it with the 'login' command",
+                    host,
+                    PASSWORD_ENV_VAR,
+                ).into()),
+            },
+        },
+    };
+
+    set_secret(&password);
+
+    Ok(password)
+}
+
+///Ask the password of the Shelly in the terminal, without displaying it
+///
+/// * `host` - The IP of the Shelly
+pub fn prompt_password(host: &str) -> Result<String, Box<dyn Error>> {
+    let password = rpassword::prompt_password(format!("Password of the Shelly {} : ", host))?;
+    set_secret(&password);
+
+    Ok(password)
+}
+
+///Save the password of the Shelly in the credential file, only readable by the current user
+///
+/// * `host` - The IP of the Shelly
+/// * `password` - The password to save
+///
+///Return the path of the credential file, or an Error if something goes wrong
+pub fn save_password(host: &str, password: &str) -> Result<PathBuf, Box<dyn Error>> {
+    let mut credentials = read_credential_file()?;
+    credentials.insert(host.to_string(), password.to_string());
+
+    let path = credential_file_path()?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+
+    //the file is created only readable by the current user, the password must never be written in a file readable by others
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    let mut file = options.open(&path)?;
+
+    //a file created by an older version may be readable by others, its permissions are fixed before writing
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+
+    file.write_all(toml::to_string(&credentials)?.as_bytes())?;
+
+    Ok(path)
+}
+
+///Replace the password by [REDACTED] in the text
+///
+/// * `text` - The text that could contain the password
+pub fn redact(text: &str) -> Cow<'_, str> {
+    match SECRET.get() {
+        Some(secret) => redact_secret(text, secret),
+        None => Cow::Borrowed(text),
+    }
+}
+
+///Replace the secret by [REDACTED] in the text, only when it is a whole token
+///
+/// A secret inside a word is kept (`x` in `Extensions`), and a secret shorter than [MIN_SECRET_LENGTH] is never replaced
+///
+/// * `text` - The text that could contain the secret
+/// * `secret` - The secret to remove
+fn redact_secret<'a>(text: &'a str, secret: &str) -> Cow<'a, str> {
+    if secret.chars().count() < MIN_SECRET_LENGTH || !text.contains(secret) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut redacted = String::with_capacity(text.len());
+    let mut last_end = 0;
+
+    for (start, _) in text.match_indices(secret) {
+        let end = start + secret.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+
+        if before.is_some_and(char::is_alphanumeric) || after.is_some_and(char::is_alphanumeric) {
+            continue;
+        }
+
+        redacted.push_str(&text[last_end..start]);
+        redacted.push_str(REDACTED);
+        last_end = end;
+    }
+
+    if last_end == 0 {
+        return Cow::Borrowed(text);
+    }
+
+    redacted.push_str(&text[last_end..]);
+    Cow::Owned(redacted)
+}
+
+///Remember the password, to remove it from the log output
+///
+/// * `password` - The password currently used
+fn set_secret(password: &str) {
+    let _ = SECRET.set(password.to_string());
+}
+
+///Get the path of the credential file in the user config directory
+fn credential_file_path() -> Result<PathBuf, Box<dyn Error>> {
+    let config_dir = dirs::config_dir().ok_or("Unable to find the config directory of the user")?;
+
+    Ok(config_dir.join(CONFIG_DIR_NAME).join(CREDENTIAL_FILE))
+}
+
+///Read the password of each host in the credential file, return nothing if the file doesn't exist
+fn read_credential_file() -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let path = credential_file_path()?;
+    if !path.is_file() {
+        return Ok(BTreeMap::new());
+    }
+
+    let content = fs::read_to_string(&path)?;
+
+    Ok(toml::from_str(&content)
+        .map_err(|error| format!("Unable to parse the credential file {} -> {}", path.display(), error))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redact_replaces_the_whole_tokens() {
+        assert_eq!(redact_secret("--password s3cret", "s3cret"), "--password ********");
+        assert_eq!(redact_secret("{\"pass\":\"s3cret\"} s3cret", "s3cret"), "{\"pass\":\"********\"} ********");
+    }
+
+    #[test]
+    fn redact_keeps_the_secret_inside_a_word() {
+        assert_eq!(redact_secret("Extensions: js", "tens"), "Extensions: js");
+        assert_eq!(redact_secret("s3crets s3cret", "s3cret"), "s3crets ********");
+    }
+
+    #[test]
+    fn redact_skips_the_short_secrets() {
+        assert!(matches!(redact_secret("Extensions x", "x"), Cow::Borrowed("Extensions x")));
+        assert!(matches!(redact_secret("the pin is 123", "123"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn redact_borrows_the_text_without_secret() {
+        assert!(matches!(redact_secret("nothing to hide", "s3cret"), Cow::Borrowed(_)));
+    }
+}
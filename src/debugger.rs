@@ -1,31 +1,71 @@
-use std::thread;
+use std::path::Path;
+use colored::Colorize;
 use log::{error, info};
-use crate::file_checker;
-use crate::logger::Logger;
+use prettytable::{row, Table};
+use crate::cli_error::CliError;
+use crate::config::Config;
+use crate::file_checker::{FileChecker, SyncReport, SyncStatus};
+use crate::log_file::LogFile;
+use crate::logger::{LogFilter, Logger};
 
 ///When called will start logging from the websocket
 ///
-/// * `path` - the path of the directory that will be check for update
-/// * `ws_port` - the websocket port
-/// * `autorun` - the autorun parameter
-pub fn debug(path: &str, ws_port: i32, autorun: bool) {
+/// * `config` - the [Config] used to communicate with the Shelly, its scripts directory will be check for update
+/// * `poll` - if the directory is checked every 0.5 seconds instead of watching the file system events
+/// * `sync` - if the files different from their script on the Shelly are uploaded on startup
+/// * `file_checker` - the [FileChecker] that will upload the edited files
+/// * `log_filter` - the [LogFilter] selecting the logs of the Shelly that are printed
+/// * `log_file` - the [LogFile] where the printed logs are also saved
+///
+///Return an Error if the scripts directory doesn't exist
+pub fn debug(config: &Config, poll: bool, sync: bool, mut file_checker: FileChecker, log_filter: LogFilter, log_file: Option<LogFile>) -> Result<(), CliError> {
+    let path = &config.scripts_dir;
 
-    std::env::set_var("shelly-port", ws_port.to_string());
-    std::env::set_var("shelly-autorun", autorun.to_string());
+    if !Path::new(path).is_dir() {
+        return Err(CliError::BadArgs(format!("The directory {} doesn't exist", path)));
+    }
 
     info!("Path : {}", path);
-    info!("WS Port : {}", ws_port);
-    info!("Autorun : {}", autorun);
-
-    ///[thread::spawn()] create a new thread and move it, like this it can be independent
-    thread::spawn(move || {
-        let logger = Logger::new();
-        if logger.is_ok() {
-            let error = logger.unwrap().start();
-            error!("Something goes wrong and kill the logger, please restart the app -> {}", error.unwrap_err());
-        } else {
-            error!("Failed to start the logger -> {}", logger.unwrap_err());
+    info!("WS Port : {}", config.ws_port);
+    info!("Device log level : {}", config.device_log_level);
+    info!("Autorun : {}", config.autorun);
+    info!("Enable on upload : {}", config.enable);
+    info!("Chunk size : {}", config.chunk_size);
+    info!("Polling : {}", poll);
+    info!("Sync on startup : {}", sync);
+
+    Logger::new(config)
+        .with_filter(log_filter)
+        .with_log_file(log_file)
+        .spawn();
+
+    if sync {
+        match file_checker.sync(path) {
+            Ok(reports) => print_sync_reports(&reports),
+            Err(error) => error!("Failed to synchronise the files with the Shelly -> {}", error),
         }
+    }
+
+    file_checker.start(path, poll);
+    Ok(())
+}
+
+///Print the result of the synchronisation of each file with the Shelly
+///
+/// * `reports` - the [SyncReport] of each file
+fn print_sync_reports(reports: &[SyncReport]) {
+    let mut table = Table::new();
+    table.add_row(row!["File".blue(), "Script".blue(), "Status".blue()]);
+
+    reports.iter().for_each(|report| {
+        let status = match report.status {
+            SyncStatus::Created | SyncStatus::Updated => report.status.to_string().yellow(),
+            SyncStatus::UpToDate => report.status.to_string().green(),
+            SyncStatus::Conflict | SyncStatus::Failed(_) => report.status.to_string().red(),
+        };
+
+        table.add_row(row![report.file, report.script, status]);
     });
-    file_checker::FileChecker::new().start(path);
+
+    table.printstd();
 }
\ No newline at end of file
@@ -1,76 +1,560 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::error::Error;
+use std::ffi::OsStr;
+use std::fmt::{Display, Formatter};
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
 use std::thread;
 use std::fs;
 use std::time::Duration;
-use filetime::FileTime;
-use log::{debug, error};
-use crate::service::shelly_rest_api::save_script_to_shelly;
+use clap::ValueEnum;
+use ignore::WalkBuilder;
+use log::{debug, error, info, warn};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult};
+use notify_debouncer_full::notify::{EventKind, RecursiveMode};
+use crate::config::Config;
+use crate::service::shelly_rest_api::{delete_script_from_shelly, save_script_to_shelly, Script, Shelly};
 
-///The File checker will check every 0.5 seconds have been created or updated
+///The delay in milliseconds between two checks of the directory when polling
+const POLLING_INTERVAL: u64 = 500;
+
+///The delay in milliseconds the watcher wait without new event before checking the directory,
+///like this an editor writing a temporary file and renaming it only trigger one upload
+const WATCHER_DEBOUNCE: u64 = 300;
+
+///The name of the file containing the patterns of the files that will not be uploaded, in addition to the `.gitignore`
+const SHELLY_IGNORE_FILE: &str = ".shellyignore";
+
+///The extension of the files that are always uploaded to the Shelly
+pub const SCRIPT_EXTENSION: &str = "js";
+
+///How the path of a file is mapped to the name of its script on the Shelly
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum NamingRule {
+    ///The path relative to the checked directory, with the folders joined by the separator (`lights/motion.js` -> `lights_motion`)
+    Path,
+    ///Only the name of the file (`lights/motion.js` -> `motion`)
+    Stem,
+}
+
+///Map the path of a file to the name of its script on the Shelly
+#[derive(Debug, Clone)]
+pub struct ScriptNaming {
+    ///The rule used to create the name
+    pub rule: NamingRule,
+    ///The separator placed between the folders when using [NamingRule::Path]
+    pub separator: String,
+}
+
+impl ScriptNaming {
+    ///Return the name of the script of the file, or [None] if the file is not in the checked directory
+    ///
+    /// * `root` - The checked directory
+    /// * `file_path` - The path of the file
+    pub fn script_name(&self, root: &Path, file_path: &Path) -> Option<String> {
+        let relative_path = file_path.strip_prefix(root).ok()?;
+        let stem = relative_path.file_stem()?.to_str()?.to_string();
+
+        match self.rule {
+            NamingRule::Stem => Some(stem),
+            NamingRule::Path => {
+                let mut parts = relative_path
+                    .parent()
+                    .map(|parent| parent
+                        .iter()
+                        .map(|part| part.to_str().map(String::from))
+                        .collect::<Option<Vec<String>>>())
+                    .unwrap_or(Some(vec![]))?;
+                parts.push(stem);
+
+                Some(parts.join(&self.separator))
+            }
+        }
+    }
+}
+
+///The result of the synchronisation of a file with its script on the Shelly
+#[derive(Debug)]
+pub enum SyncStatus {
+    ///The script was not on the Shelly and has been uploaded
+    Created,
+    ///The script on the Shelly was different and has been uploaded
+    Updated,
+    ///The script on the Shelly is the same as the file
+    UpToDate,
+    ///The script name is used by multiple files, nothing has been uploaded
+    Conflict,
+    ///Something goes wrong during the synchronisation
+    Failed(String),
+}
+
+impl Display for SyncStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncStatus::Created => write!(f, "Created"),
+            SyncStatus::Updated => write!(f, "Updated"),
+            SyncStatus::UpToDate => write!(f, "Up to date"),
+            SyncStatus::Conflict => write!(f, "Name used by multiple files"),
+            SyncStatus::Failed(error) => write!(f, "Failed -> {}", error),
+        }
+    }
+}
+
+///The synchronisation report of one file
+#[derive(Debug)]
+pub struct SyncReport {
+    ///The path of the file
+    pub file: String,
+    ///The name of the script on the Shelly
+    pub script: String,
+    ///What happened during the synchronisation
+    pub status: SyncStatus,
+}
+
+///Hash the content of a file, to know if it has changed without keeping all the content
+///
+/// * `content` - The raw bytes to hash, like this the files that are not valid UTF-8 can be hashed too
+pub fn hash_content(content: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+///The File checker will check when files have been created, updated or removed,
+///by watching the events of the file system or by checking the directory every 0.5 seconds
+#[derive(Debug)]
 pub struct FileChecker {
-    last_modified: HashMap<String, FileTime>,
+    ///The hash of the content of each file at the last check
+    last_seen: HashMap<String, u64>,
+    ///The hash of the content last uploaded for each script, or known to be the same as on the Shelly
+    uploaded: HashMap<String, u64>,
+    ///[false] until the first check, the files already there are considered to be the same as on the Shelly
+    initialized: bool,
+    ///If [true], the script of a removed file will be deleted from the Shelly
+    delete_removed: bool,
+    ///If [true], only log the scripts that would be deleted from the Shelly
+    dry_run: bool,
+    ///If [true], the files in the sub directories are checked too
+    recursive: bool,
+    ///The extensions of the files uploaded to the Shelly, without the dot
+    extensions: Vec<String>,
+    ///How the path of a file is mapped to the name of its script
+    naming: ScriptNaming,
+    ///The Shelly where the files are uploaded
+    shelly: Shelly,
 }
 
 impl FileChecker {
     ///Create a new instance of [FileChecker]
-    pub fn new() -> Self {
+    ///
+    /// * `config` - The [Config] used to communicate with the Shelly
+    /// * `delete_removed` - If [true], the script of a removed file will be deleted from the Shelly
+    /// * `dry_run` - If [true], only log the scripts that would be deleted from the Shelly
+    /// * `recursive` - If [true], the files in the sub directories are checked too
+    /// * `extensions` - The extensions of the files uploaded to the Shelly in addition to [SCRIPT_EXTENSION]
+    /// * `naming` - How the path of a file is mapped to the name of its script
+    pub fn new(config: &Config, delete_removed: bool, dry_run: bool, recursive: bool, extensions: &[String], naming: ScriptNaming) -> Self {
+        let mut all_extensions = vec![SCRIPT_EXTENSION.to_string()];
+        for extension in extensions.iter().map(|extension| extension.trim_start_matches('.')) {
+            if !extension.is_empty() && !all_extensions.iter().any(|known| known == extension) {
+                all_extensions.push(extension.to_string());
+            }
+        }
+
         FileChecker {
-            last_modified: HashMap::new(),
+            last_seen: HashMap::new(),
+            uploaded: HashMap::new(),
+            initialized: false,
+            delete_removed,
+            dry_run,
+            recursive,
+            extensions: all_extensions,
+            naming,
+            shelly: Shelly::new(config),
         }
     }
 
     ///Start the [FileChecker]
     ///
     /// * `path_to_check` - The path where the [FileChecker] will check if some file has been created or updated
-    pub fn start(&mut self, path_to_check: &str) {
+    /// * `polling` - If [true], check the directory every 0.5 seconds instead of watching the file system events (useful on network file systems)
+    pub fn start(&mut self, path_to_check: &str, polling: bool) {
+        info!("Recursive : {}", self.recursive);
+        info!("Extensions : {}", self.extensions.join(", "));
+        info!("Naming : {:?} (separator '{}')", self.naming.rule, self.naming.separator);
+        info!("Delete removed scripts : {}{}", self.delete_removed, if self.dry_run { " (dry run)" } else { "" });
+
+        if !polling {
+            let Err(error) = self.watch(path_to_check);
+            warn!("Unable to watch the directory, fallback to polling -> {}", error);
+        }
+
+        self.poll(path_to_check);
+    }
+
+    ///Upload the files that are different from their script on the Shelly, comparing the hash of their content
+    ///
+    /// * `path_to_check` - The path where the [FileChecker] will look for the files to synchronise
+    ///
+    ///Return a [SyncReport] for each file, or an Error if the scripts can't be listed
+    pub fn sync(&mut self, path_to_check: &str) -> Result<Vec<SyncReport>, Box<dyn Error>> {
+        let root = Path::new(path_to_check);
+        let files = self.list_files(root);
+        let scripts = self.map_script_names(root, &files);
+
+        let available_scripts = self.shelly.script_list()?;
+        let mut reports = Vec::new();
+
+        for file_path in files {
+            let path = file_path.display().to_string();
+            let Some(script_name) = self.naming.script_name(root, &file_path) else {
+                continue;
+            };
+
+            let status = match fs::read_to_string(&file_path) {
+                Err(error) => SyncStatus::Failed(error.to_string()),
+                Ok(_) if scripts.get(&script_name).is_some_and(|paths| paths.len() > 1) => SyncStatus::Conflict,
+                Ok(content) => {
+                    let hash = hash_content(content.as_bytes());
+                    self.last_seen.insert(path.clone(), hash);
+
+                    match self.sync_file(&available_scripts, &path, &script_name, &content) {
+                        Ok(status) => {
+                            self.uploaded.insert(script_name.clone(), hash);
+                            status
+                        }
+                        Err(error) => SyncStatus::Failed(error.to_string()),
+                    }
+                }
+            };
+
+            debug!("{} -> {} : {}", path, script_name, status);
+            reports.push(SyncReport {
+                file: path,
+                script: script_name,
+                status,
+            });
+        }
+
+        Ok(reports)
+    }
+
+    ///Upload the file if it is different from its script on the Shelly
+    ///
+    /// * `available_scripts` - The scripts currently on the Shelly
+    /// * `path` - The path of the file
+    /// * `script_name` - The name of the script of the file
+    /// * `content` - The content of the file
+    fn sync_file(&self, available_scripts: &[Script], path: &str, script_name: &str, content: &str) -> Result<SyncStatus, Box<dyn Error>> {
+        let status = match available_scripts.iter().find(|script| script.name == script_name) {
+            None => SyncStatus::Created,
+            Some(script) => {
+                let device_content = self.shelly.script_get_code(script)?;
+                if hash_content(device_content.as_bytes()) == hash_content(content.as_bytes()) {
+                    return Ok(SyncStatus::UpToDate);
+                }
+
+                SyncStatus::Updated
+            }
+        };
+
+        save_script_to_shelly(&self.shelly, path, script_name)?;
 
+        Ok(status)
+    }
+
+    ///Check the directory every [POLLING_INTERVAL] milliseconds
+    ///
+    /// * `path_to_check` - The path where the [FileChecker] will check if some file has been created or updated
+    fn poll(&mut self, path_to_check: &str) {
         loop {
-            let result = self.check_directory(path_to_check);
+            self.check(path_to_check);
+
+            thread::sleep(Duration::from_millis(POLLING_INTERVAL));
+        }
+    }
+
+    ///Check the directory each time the file system report events in it, after [WATCHER_DEBOUNCE] milliseconds without new event
+    ///
+    /// * `path_to_check` - The path where the [FileChecker] will check if some file has been created or updated
+    ///
+    ///Only return when the watcher can't be started or has been stopped, so it never return [Ok]
+    fn watch(&mut self, path_to_check: &str) -> Result<Infallible, Box<dyn Error>> {
+        let recursive_mode = if self.recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+
+        let (sender, receiver) = mpsc::channel::<DebounceEventResult>();
+        let mut debouncer = new_debouncer(Duration::from_millis(WATCHER_DEBOUNCE), None, sender)?;
+        debouncer.watch(Path::new(path_to_check), recursive_mode)?;
 
+        self.check(path_to_check);
+
+        for result in receiver {
             match result {
-                Err(error) => {
-                    error!("Somthing goes wrong when processing files in the directory -> {}", error);
+                Ok(events) => {
+                    //reading the directory trigger access events, they have to be ignored to not check it in loop
+                    let changed_paths: Vec<_> = events
+                        .iter()
+                        .filter(|event| !matches!(event.kind, EventKind::Access(_)))
+                        .flat_map(|event| event.paths.iter())
+                        .collect();
+
+                    if changed_paths.is_empty() {
+                        continue;
+                    }
+
+                    changed_paths.iter().for_each(|path| debug!("{} has changed", path.display()));
+                    self.check(path_to_check);
                 }
-                _ => {}
+                Err(errors) => errors
+                    .iter()
+                    .for_each(|error| error!("Something goes wrong when watching the directory -> {}", error)),
             }
+        }
 
-            thread::sleep(Duration::from_millis(500));
+        Err("the watcher has been stopped".into())
+    }
+
+    ///Check the directory and log the error if something goes wrong
+    ///
+    /// * `path_to_check` - The path where the [FileChecker] will check if some file has been created or updated
+    fn check(&mut self, path_to_check: &str) {
+        if let Err(error) = self.check_directory(path_to_check) {
+            error!("Somthing goes wrong when processing files in the directory -> {}", error);
         }
     }
 
-    ///Check if any file has been created or updated
+    ///Check if any file has been created, updated or removed
+    ///
+    /// A file is uploaded only when its content is different from the last content uploaded for its script,
+    /// if the upload fails it will be retried on the next change of the file
     ///
     /// * `directory_to_check` - The path where the [FileChecker] will check if some file has been created or updated
     fn check_directory(&mut self, directory_to_check: &str) -> Result<(), Box<dyn Error>>{
+        let root = Path::new(directory_to_check);
+        let files = self.list_files(root);
+        let scripts = self.map_script_names(root, &files);
+        let mut existing_files = HashSet::new();
 
-        let files_path = fs::read_dir(directory_to_check)?;
+        for file_path in files {
+            let Some(path) = file_path.to_str() else {
+                error!("Unable to check {}, its path is not valid UTF-8", file_path.display());
+                continue;
+            };
+            existing_files.insert(path.to_string());
 
-        for file in files_path {
-            let file_info = file?;
-            let file_path = file_info.path();
-            let path = file_path.to_str().ok_or("")?;
+            let Some(script_name) = self.naming.script_name(root, &file_path) else {
+                continue;
+            };
 
-            let metadata = fs::metadata(path)?;
+            //a file that can't be read is skipped, like this it doesn't stop the check of the other files
+            let hash = match fs::read(path) {
+                Ok(content) => hash_content(&content),
+                Err(error) => {
+                    error!("Unable to read {}, it will be checked again on the next change -> {}", path, error);
+                    continue;
+                }
+            };
+            let last_hash = self.last_seen.insert(path.to_string(), hash);
 
-            if metadata.is_file() {
-                let modif_time = FileTime::from_last_modification_time(&metadata);
-                let last_time_result= self.last_modified.get(path);
+            if last_hash == Some(hash) {
+                continue;
+            }
 
-                match last_time_result {
-                    None => { self.last_modified.insert(path.to_string(), modif_time); }
-                    Some(last_modif_time) => {
-                        if &modif_time > last_modif_time {
-                            debug!("{} has been modified !", &file_info.path().display());
-                            self.last_modified.insert(path.to_string(), modif_time);
-                            save_script_to_shelly(path)?;
-                        }
-                    }
-                }
+            if last_hash.is_none() && !self.initialized {
+                self.uploaded.entry(script_name).or_insert(hash);
+                continue;
+            }
+
+            if self.uploaded.get(&script_name) == Some(&hash) {
+                debug!("{} has been modified, but is the same as on the Shelly", file_path.display());
+                continue;
+            }
+
+            debug!("{} has been modified !", file_path.display());
+
+            if let Some(paths) = scripts.get(&script_name).filter(|paths| paths.len() > 1) {
+                error!("Unable to upload {}, the script name {} is used by multiple files -> {}", path, script_name, paths.join(", "));
+                continue;
+            }
+
+            match save_script_to_shelly(&self.shelly, path, &script_name) {
+                Ok(_) => { self.uploaded.insert(script_name, hash); }
+                Err(error) => error!("Failed to upload {}, it will be retried on the next change -> {}", path, error),
             }
         }
 
+        self.initialized = true;
+
+        let removed_files: Vec<String> = self.last_seen
+            .keys()
+            .filter(|path| !existing_files.contains(*path))
+            .cloned()
+            .collect();
+
+        for path in removed_files {
+            //a file that still exist has only been ignored, its script is kept on the Shelly
+            if Path::new(&path).exists() {
+                debug!("{} is now ignored", path);
+                self.last_seen.remove(&path);
+                continue;
+            }
+
+            debug!("{} has been removed !", path);
+
+            if !self.delete_removed {
+                self.last_seen.remove(&path);
+                continue;
+            }
+
+            let Some(script_name) = self.naming.script_name(root, Path::new(&path)) else {
+                self.last_seen.remove(&path);
+                continue;
+            };
+
+            if scripts.contains_key(&script_name) {
+                warn!("{} has been removed, but the script {} is still used by another file, it will not be deleted", path, script_name);
+            } else if self.dry_run {
+                info!("{} has been removed, the script {} would be deleted from the Shelly (dry run)", path, script_name);
+            } else if let Err(error) = delete_script_from_shelly(&self.shelly, &script_name) {
+                //the file is kept as last seen, like this the deletion is retried on the next check
+                error!("Failed to delete the script {} of the removed file {}, it will be retried on the next check", script_name, path);
+                error!("Due to -> {}", error);
+                continue;
+            } else {
+                self.uploaded.remove(&script_name);
+            }
+
+            self.last_seen.remove(&path);
+        }
+
         Ok(())
     }
+
+    ///List the script files in the directory, and in its sub directories if the [FileChecker] is recursive
+    ///
+    /// The files matching a pattern of the `.gitignore` or of the [SHELLY_IGNORE_FILE] are skipped
+    ///
+    /// * `directory` - The directory to list
+    fn list_files(&self, directory: &Path) -> Vec<PathBuf> {
+        let max_depth = if self.recursive { None } else { Some(1) };
+        let walker = WalkBuilder::new(directory)
+            .add_custom_ignore_filename(SHELLY_IGNORE_FILE)
+            .require_git(false)
+            .follow_links(true)
+            .max_depth(max_depth)
+            .build();
+
+        let mut files = Vec::new();
+
+        for entry in walker {
+            //a broken symlink or an unreadable directory must not stop the check of the other files
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    error!("Unable to read an entry of the directory, it is skipped");
+                    error!("Due to -> {}", error);
+                    continue;
+                }
+            };
+            let is_file = entry.file_type().is_some_and(|file_type| file_type.is_file());
+
+            if is_file && self.is_script(entry.path()) {
+                files.push(entry.into_path());
+            }
+        }
+
+        files
+    }
+
+    ///Check if the file has one of the extensions uploaded to the Shelly
+    ///
+    /// * `file_path` - The path of the file
+    fn is_script(&self, file_path: &Path) -> bool {
+        file_path
+            .extension()
+            .and_then(OsStr::to_str)
+            .is_some_and(|extension| self.extensions.iter().any(|allowed| allowed == extension))
+    }
+
+    ///Group the script files by the name of their script, to find the files that would override each other
+    ///
+    /// * `root` - The checked directory
+    /// * `files` - The script files in the checked directory
+    fn map_script_names(&self, root: &Path, files: &[PathBuf]) -> HashMap<String, Vec<String>> {
+        let mut scripts: HashMap<String, Vec<String>> = HashMap::new();
+
+        files
+            .iter()
+            .for_each(|file_path| {
+                if let Some(script_name) = self.naming.script_name(root, file_path) {
+                    scripts.entry(script_name).or_default().push(file_path.display().to_string());
+                }
+            });
+
+        scripts
+    }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn naming(rule: NamingRule, separator: &str) -> ScriptNaming {
+        ScriptNaming {
+            rule,
+            separator: separator.to_string(),
+        }
+    }
+
+    fn script_name(naming: &ScriptNaming, root: &str, file_path: &str) -> Option<String> {
+        naming.script_name(Path::new(root), Path::new(file_path))
+    }
+
+    #[test]
+    fn path_naming_uses_the_file_stem_at_the_root() {
+        let naming = naming(NamingRule::Path, "_");
+
+        assert_eq!(script_name(&naming, "scripts", "scripts/motion.js"), Some("motion".to_string()));
+        assert_eq!(script_name(&naming, "scripts", "scripts/motion.min.js"), Some("motion.min".to_string()));
+    }
+
+    #[test]
+    fn path_naming_joins_the_nested_folders_with_the_separator() {
+        assert_eq!(script_name(&naming(NamingRule::Path, "_"), "scripts", "scripts/lights/motion.js"), Some("lights_motion".to_string()));
+        assert_eq!(script_name(&naming(NamingRule::Path, "-"), "scripts", "scripts/home/lights/motion.js"), Some("home-lights-motion".to_string()));
+        assert_eq!(script_name(&naming(NamingRule::Path, ""), "scripts", "scripts/lights/motion.js"), Some("lightsmotion".to_string()));
+    }
+
+    #[test]
+    fn path_naming_strips_the_root_prefix() {
+        let naming = naming(NamingRule::Path, "_");
+
+        assert_eq!(script_name(&naming, "./", "./lights/motion.js"), Some("lights_motion".to_string()));
+        assert_eq!(script_name(&naming, ".", "./lights/motion.js"), Some("lights_motion".to_string()));
+        assert_eq!(script_name(&naming, "project/scripts/", "project/scripts/lights/motion.js"), Some("lights_motion".to_string()));
+    }
+
+    #[test]
+    fn stem_naming_ignores_the_folders() {
+        let naming = naming(NamingRule::Stem, "_");
+
+        assert_eq!(script_name(&naming, "scripts", "scripts/motion.js"), Some("motion".to_string()));
+        assert_eq!(script_name(&naming, "scripts", "scripts/home/lights/motion.js"), Some("motion".to_string()));
+    }
+
+    #[test]
+    fn files_outside_of_the_root_have_no_name() {
+        let naming = naming(NamingRule::Path, "_");
+
+        assert_eq!(script_name(&naming, "scripts", "other/motion.js"), None);
+        assert_eq!(script_name(&naming, "scripts/lights", "scripts/motion.js"), None);
+        assert_eq!(script_name(&naming, "scripts", "scripts"), None);
+    }
+}
@@ -0,0 +1,290 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Local, NaiveDate};
+use clap::ValueEnum;
+use log::debug;
+
+///How the logs of the Shelly are written in the log file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFileFormat {
+    ///The same lines as in the console, without colors
+    Text,
+    ///The raw JSON messages sent by the Shelly, one per line
+    Json,
+}
+
+///When the log file is rotated, the current file is renamed `<file>.1` and a new one is started
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogRotation {
+    ///Rotate the file when it would be larger than the maximum size
+    Size,
+    ///Rotate the file each day
+    Daily,
+    ///Never rotate the file
+    Never,
+}
+
+///A log file where the logs of the Shelly are saved, rotated with [LogRotation]
+#[derive(Debug)]
+pub struct LogFile {
+    ///The path of the current log file
+    path: PathBuf,
+    ///How the logs are written
+    format: LogFileFormat,
+    ///When the file is rotated
+    rotation: LogRotation,
+    ///The maximum size in bytes of the file with [LogRotation::Size]
+    max_size: u64,
+    ///How many rotated files are kept, the older ones are deleted
+    keep: usize,
+    ///The current log file
+    file: File,
+    ///The current size in bytes of the file
+    size: u64,
+    ///The day when the current file has been started
+    date: NaiveDate,
+}
+
+impl LogFile {
+    ///Open the log file, the logs are appended to it if it already exists
+    ///
+    /// * `path` - The path of the log file
+    /// * `format` - How the logs are written
+    /// * `rotation` - When the file is rotated
+    /// * `max_size` - The maximum size in bytes of the file with [LogRotation::Size]
+    /// * `keep` - How many rotated files are kept
+    ///
+    ///Return an Error if the file can't be opened
+    pub fn open(path: &str, format: LogFileFormat, rotation: LogRotation, max_size: u64, keep: usize) -> io::Result<Self> {
+        let path = PathBuf::from(path);
+        if let Some(directory) = path.parent().filter(|directory| !directory.as_os_str().is_empty()) {
+            fs::create_dir_all(directory)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let metadata = file.metadata()?;
+        let date = metadata
+            .modified()
+            .map(|modified| DateTime::<Local>::from(modified).date_naive())
+            .unwrap_or_else(|_| Local::now().date_naive());
+
+        Ok(LogFile {
+            path,
+            format,
+            rotation,
+            max_size,
+            keep,
+            file,
+            size: metadata.len(),
+            date,
+        })
+    }
+
+    ///Write a log of the Shelly in the file, rotating it before when needed
+    ///
+    /// * `raw` - The JSON message sent by the Shelly, written with [LogFileFormat::Json]
+    /// * `text` - The formatted log, written with [LogFileFormat::Text]
+    pub fn write(&mut self, raw: &str, text: &str) -> io::Result<()> {
+        let line = match self.format {
+            LogFileFormat::Text => format!("{}\n", text),
+            LogFileFormat::Json => format!("{}\n", raw.trim_end()),
+        };
+
+        let today = Local::now().date_naive();
+        let rotate = match self.rotation {
+            LogRotation::Size => self.size > 0 && self.size + line.len() as u64 > self.max_size,
+            LogRotation::Daily => today != self.date,
+            LogRotation::Never => false,
+        };
+        if rotate {
+            self.rotate()?;
+            self.date = today;
+        }
+
+        self.file.write_all(line.as_bytes())?;
+        self.size += line.len() as u64;
+
+        Ok(())
+    }
+
+    ///Rename the current file `<file>.1`, the previous `<file>.1` becomes `<file>.2` ... and the files
+    ///after `<file>.<keep>` are deleted, then start a new file
+    fn rotate(&mut self) -> io::Result<()> {
+        debug!("Rotating the log file {}", self.path.display());
+
+        self.file.flush()?;
+
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            let oldest = self.rotated_path(self.keep);
+            if oldest.exists() {
+                fs::remove_file(&oldest)?;
+            }
+
+            for index in (1..self.keep).rev() {
+                let rotated = self.rotated_path(index);
+                if rotated.exists() {
+                    fs::rename(&rotated, self.rotated_path(index + 1))?;
+                }
+            }
+
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+    ///Get the path of a rotated file, like `shelly.log.1`
+    ///
+    /// * `index` - The index of the rotated file, `1` is the most recent
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!(".{}", index));
+
+        self.path.parent().unwrap_or(Path::new("")).join(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Days;
+
+    ///Create an empty directory for the test and return the path of its log file
+    fn log_path(test: &str) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("shelly_log_file_{}_{}", test, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(&directory).unwrap();
+
+        directory.join("shelly.log")
+    }
+
+    fn open(path: &Path, rotation: LogRotation, max_size: u64, keep: usize) -> LogFile {
+        LogFile::open(path.to_str().unwrap(), LogFileFormat::Text, rotation, max_size, keep).unwrap()
+    }
+
+    fn read(path: &Path) -> Option<String> {
+        fs::read_to_string(path).ok()
+    }
+
+    fn rotated(path: &Path, index: usize) -> PathBuf {
+        PathBuf::from(format!("{}.{}", path.display(), index))
+    }
+
+    #[test]
+    fn write_uses_the_format_of_the_file() {
+        let path = log_path("format");
+
+        let mut text = LogFile::open(path.to_str().unwrap(), LogFileFormat::Text, LogRotation::Never, 0, 0).unwrap();
+        text.write("{\"data\":\"hello\"}\n", "[INFO] hello").unwrap();
+        assert_eq!(read(&path).unwrap(), "[INFO] hello\n");
+
+        let json_path = path.with_file_name("shelly.jsonl");
+        let mut json = LogFile::open(json_path.to_str().unwrap(), LogFileFormat::Json, LogRotation::Never, 0, 0).unwrap();
+        json.write("{\"data\":\"hello\"}\n", "[INFO] hello").unwrap();
+        assert_eq!(read(&json_path).unwrap(), "{\"data\":\"hello\"}\n");
+    }
+
+    #[test]
+    fn open_appends_to_an_existing_file() {
+        let path = log_path("append");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut log_file = open(&path, LogRotation::Size, 100, 1);
+        assert_eq!(log_file.size, 4);
+
+        log_file.write("", "new").unwrap();
+        assert_eq!(read(&path).unwrap(), "old\nnew\n");
+    }
+
+    #[test]
+    fn size_rotation_keeps_a_too_large_line_in_an_empty_file() {
+        let path = log_path("large_line");
+        let mut log_file = open(&path, LogRotation::Size, 4, 2);
+
+        log_file.write("", "larger than the maximum size").unwrap();
+        assert_eq!(read(&path).unwrap(), "larger than the maximum size\n");
+        assert!(!rotated(&path, 1).exists());
+
+        log_file.write("", "next").unwrap();
+        assert_eq!(read(&path).unwrap(), "next\n");
+        assert_eq!(read(&rotated(&path, 1)).unwrap(), "larger than the maximum size\n");
+    }
+
+    #[test]
+    fn size_rotation_shifts_the_rotated_files_and_deletes_the_oldest() {
+        let path = log_path("shift");
+        let mut log_file = open(&path, LogRotation::Size, 10, 2);
+
+        //each line fills the file, so each new line rotates it
+        for line in ["line 1", "line 2", "line 3", "line 4"] {
+            log_file.write("", line).unwrap();
+        }
+
+        assert_eq!(read(&path).unwrap(), "line 4\n");
+        assert_eq!(read(&rotated(&path, 1)).unwrap(), "line 3\n");
+        assert_eq!(read(&rotated(&path, 2)).unwrap(), "line 2\n");
+        assert!(!rotated(&path, 3).exists());
+    }
+
+    #[test]
+    fn size_rotation_waits_for_the_maximum_size() {
+        let path = log_path("max_size");
+        let mut log_file = open(&path, LogRotation::Size, 14, 2);
+
+        log_file.write("", "line 1").unwrap();
+        log_file.write("", "line 2").unwrap();
+        assert_eq!(read(&path).unwrap(), "line 1\nline 2\n");
+        assert!(!rotated(&path, 1).exists());
+
+        log_file.write("", "line 3").unwrap();
+        assert_eq!(read(&path).unwrap(), "line 3\n");
+        assert_eq!(read(&rotated(&path, 1)).unwrap(), "line 1\nline 2\n");
+    }
+
+    #[test]
+    fn rotation_without_keeping_files_starts_a_new_file() {
+        let path = log_path("keep_0");
+        let mut log_file = open(&path, LogRotation::Size, 10, 0);
+
+        log_file.write("", "line 1").unwrap();
+        log_file.write("", "line 2").unwrap();
+
+        assert_eq!(read(&path).unwrap(), "line 2\n");
+        assert!(!rotated(&path, 1).exists());
+    }
+
+    #[test]
+    fn daily_rotation_starts_a_new_file_each_day() {
+        let path = log_path("daily");
+        let mut log_file = open(&path, LogRotation::Daily, 0, 2);
+
+        log_file.write("", "today 1").unwrap();
+        log_file.write("", "today 2").unwrap();
+        assert_eq!(read(&path).unwrap(), "today 1\ntoday 2\n");
+
+        log_file.date = log_file.date.checked_sub_days(Days::new(1)).unwrap();
+        log_file.write("", "tomorrow").unwrap();
+
+        assert_eq!(read(&path).unwrap(), "tomorrow\n");
+        assert_eq!(read(&rotated(&path, 1)).unwrap(), "today 1\ntoday 2\n");
+        assert_eq!(log_file.date, Local::now().date_naive());
+    }
+
+    #[test]
+    fn never_rotation_keeps_one_file() {
+        let path = log_path("never");
+        let mut log_file = open(&path, LogRotation::Never, 1, 2);
+
+        log_file.write("", "line 1").unwrap();
+        log_file.write("", "line 2").unwrap();
+
+        assert_eq!(read(&path).unwrap(), "line 1\nline 2\n");
+        assert!(!rotated(&path, 1).exists());
+    }
+}
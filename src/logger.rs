@@ -1,10 +1,132 @@
-use std::error::Error;
-use std::thread;
+use std::cell::RefCell;
+use std::collections::hash_map::RandomState;
+use std::fmt::{Display, Formatter};
+use std::hash::BuildHasher;
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
-use log::{debug, error, info};
+use chrono::{DateTime, Local};
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use log::{debug, error, info, warn};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use tungstenite::connect;
+use tungstenite::{connect, Message};
 use url::Url;
+use crate::config::Config;
+use crate::log_file::LogFile;
+use crate::service::shelly_rest_api::ShellyError;
+
+///The delay in milliseconds before the first reconnection to the websocket
+const RECONNECT_MIN_DELAY: u64 = 500;
+
+///The maximum delay in milliseconds between two connections to the websocket
+const RECONNECT_MAX_DELAY: u64 = 30_000;
+
+///The maximum random jitter added to the delay, in percent of the delay
+const RECONNECT_JITTER: u64 = 25;
+
+///The severity of a log message of the Shelly, from the most to the least important
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
+pub enum DeviceLogLevel {
+    ///Something failed on the Shelly
+    Error,
+    ///Something unexpected happened on the Shelly
+    Warn,
+    ///The normal messages, like the output of `print()` in the scripts
+    Info,
+    ///The details of what the firmware does
+    Debug,
+    ///Everything the firmware logs
+    Verbose,
+}
+
+impl DeviceLogLevel {
+    ///Get the severity of a level sent by the Shelly
+    ///
+    /// The Shelly sends `0` (error) to `4` (verbose), the output of the scripts is sent with `-1`
+    ///
+    /// * `level` - The level of the [LogMessage]
+    fn from_shelly(level: i64) -> Self {
+        match level {
+            ..=-1 => DeviceLogLevel::Info,
+            0 => DeviceLogLevel::Error,
+            1 => DeviceLogLevel::Warn,
+            2 => DeviceLogLevel::Info,
+            3 => DeviceLogLevel::Debug,
+            _ => DeviceLogLevel::Verbose,
+        }
+    }
+
+    ///Color the text with the color of the level
+    ///
+    /// * `text` - The text to color
+    fn colorize(&self, text: &str) -> ColoredString {
+        match self {
+            DeviceLogLevel::Error => text.red(),
+            DeviceLogLevel::Warn => text.yellow(),
+            DeviceLogLevel::Info => text.normal(),
+            DeviceLogLevel::Debug => text.blue(),
+            DeviceLogLevel::Verbose => text.dimmed(),
+        }
+    }
+}
+
+impl Display for DeviceLogLevel {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DeviceLogLevel::Error => "ERROR",
+            DeviceLogLevel::Warn => "WARN",
+            DeviceLogLevel::Info => "INFO",
+            DeviceLogLevel::Debug => "DEBUG",
+            DeviceLogLevel::Verbose => "TRACE",
+        };
+
+        //the padding is applied to the name, like this it can be used with `{:<5}`
+        f.pad(name)
+    }
+}
+
+///Select the messages of the Shelly that are printed, by script and by regex
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    ///Match the messages of one script, by the prefix added by the firmware
+    script: Option<Regex>,
+    ///Only the messages matching this regex are printed
+    grep: Option<Regex>,
+    ///The messages matching this regex are not printed
+    exclude: Option<Regex>,
+}
+
+impl LogFilter {
+    ///Create a new filter, the messages are printed if they match all the indicated rules
+    ///
+    /// * `script_id` - The id of the script whose messages are printed
+    /// * `grep` - The regex that the messages have to match
+    /// * `exclude` - The regex that the messages must not match
+    ///
+    ///Return an Error if a regex is not valid
+    pub fn new(script_id: Option<i32>, grep: Option<&str>, exclude: Option<&str>) -> Result<Self, regex::Error> {
+        Ok(LogFilter {
+            //the firmware prefixes the messages of a script with `script_<id>` or `shelly_script_<id>`, sometimes after
+            //the source of the message like `shelly_script.cpp:123`, only this prefix is matched and not the rest of the message
+            script: script_id
+                .map(|id| Regex::new(&format!(r"^(?:shelly_script\S*\s+)?(?:shelly_)?script_{}(?:[:\s]|$)", id)))
+                .transpose()?,
+            grep: grep.map(Regex::new).transpose()?,
+            exclude: exclude.map(Regex::new).transpose()?,
+        })
+    }
+
+    ///Check if the message has to be printed
+    ///
+    /// * `text` - The text of the message
+    fn matches(&self, text: &str) -> bool {
+        self.script.as_ref().is_none_or(|script| script.is_match(text))
+            && self.grep.as_ref().is_none_or(|grep| grep.is_match(text))
+            && !self.exclude.as_ref().is_some_and(|exclude| exclude.is_match(text))
+    }
+}
 
 ///The Logger data
 #[derive(Debug)]
@@ -12,68 +134,270 @@ pub struct Logger {
     ///The IP of the Shelly
     host: String,
     ///The websocket port
-    port: i32,
+    port: u16,
+    ///The least important level of the messages of the Shelly that are printed
+    level: DeviceLogLevel,
+    ///Select the messages of the Shelly that are printed
+    filter: LogFilter,
+    ///If [true] the logger reconnects when the connection is lost, if [false] it stops
+    follow: bool,
+    ///The logger stops after printing this number of messages, if [None] it never stops
+    max_lines: Option<usize>,
+    ///The file where the printed messages are also saved, if [None] they are only printed
+    log_file: Option<RefCell<LogFile>>,
+    ///Notified each time the logger is connected to the websocket
+    connected: Option<Sender<()>>,
 }
 
 ///Representation of a message from the websocket
 #[derive(Serialize, Deserialize)]
 struct LogMessage {
+    ///The time of the message on the Shelly, in seconds since the Unix epoch
     ts: f64,
+    ///The level of the message, see [DeviceLogLevel::from_shelly]
     level: i64,
+    ///The text of the message
     data: String,
 }
 
 impl Logger {
     ///Create a new instance of the Logger
-    pub fn new() -> Result<Self, Box<dyn Error>>{
-        Ok(Self {
-            host: std::env::var("shelly-host")?,
-            port: std::env::var("shelly-port")?.parse::<i32>()?,
+    ///
+    /// * `config` - The [Config] used to communicate with the Shelly
+    pub fn new(config: &Config) -> Self {
+        Self {
+            host: config.host.clone(),
+            port: config.ws_port,
+            level: config.device_log_level,
+            filter: LogFilter::default(),
+            follow: true,
+            max_lines: None,
+            log_file: None,
+            connected: None,
+        }
+    }
+
+    ///Only print the messages of the Shelly selected by the filter
+    ///
+    /// * `filter` - The [LogFilter] selecting the messages
+    pub fn with_filter(mut self, filter: LogFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    ///Stop the logger when the connection is lost or when enough messages have been printed
+    ///
+    /// * `follow` - If [true] the logger reconnects when the connection is lost, if [false] it stops
+    /// * `max_lines` - The number of messages printed before stopping, if [None] it never stops
+    pub fn with_limits(mut self, follow: bool, max_lines: Option<usize>) -> Self {
+        self.follow = follow;
+        self.max_lines = max_lines;
+        self
+    }
+
+    ///Also save the printed messages of the Shelly in a file
+    ///
+    /// * `log_file` - The [LogFile] where the messages are saved, if [None] they are only printed
+    pub fn with_log_file(mut self, log_file: Option<LogFile>) -> Self {
+        self.log_file = log_file.map(RefCell::new);
+        self
+    }
+
+    ///Start the logger in its own thread, like this the logs are printed while the utilitary does something else
+    pub fn spawn(self) -> JoinHandle<()> {
+        //[thread::spawn()] create a new thread and move it, like this it can be independent
+        thread::spawn(move || {
+            if let Err(error) = self.start() {
+                error!("Something goes wrong and kill the logger, please restart the app -> {}", error);
+            }
         })
     }
 
+    ///Start the logger in its own thread and wait until it is connected to the websocket, like this the
+    ///logs sent by the Shelly right after are not lost
+    ///
+    /// * `timeout` - The maximum time to wait for the connection
+    ///
+    ///Return [false] if the logger is not connected after the timeout, it keeps trying in its thread
+    pub fn spawn_connected(mut self, timeout: Duration) -> bool {
+        let (sender, receiver) = mpsc::channel();
+        self.connected = Some(sender);
+        self.spawn();
+
+        receiver.recv_timeout(timeout).is_ok()
+    }
+
     ///Start the logger and will try to connect to the shelly
-    pub fn start(&self) -> Result<(), Box<dyn Error>>{
-        let url = format!("ws://{}:{}/debug/log", self.host, self.port);
-        let mut retry_time = 0;
+    ///
+    /// When the connection fails or is lost, the logger reconnects after a delay doubled on each failed
+    /// attempt, up to [RECONNECT_MAX_DELAY], with some random jitter
+    ///
+    ///Return when the limits indicated with [Logger::with_limits] are reached, or an Error if the logger
+    ///doesn't follow the logs and can't connect to the Shelly
+    pub fn start(&self) -> Result<(), ShellyError> {
+        let url = Url::parse(&format!("ws://{}:{}/debug/log", self.host, self.port))
+            .map_err(|error| ShellyError::Transport(error.to_string()))?;
+        let mut attempt = 0;
+        let mut printed = 0;
 
         loop {
-            thread::sleep(Duration::from_secs(retry_time));
+            debug!("Connecting to the websocket {} ...", url);
 
-            let connection = connect(Url::parse(&url)?);
+            let (mut socket, _) = match connect(url.clone()) {
+                Ok(connection) => connection,
+                Err(error) if !self.follow => return Err(ShellyError::Transport(error.to_string())),
+                Err(error) => {
+                    let delay = reconnect_delay(attempt);
+                    attempt += 1;
+                    error!("Failed to listen the websocket at {}, retrying in {:.1}s ...", self.host, delay.as_secs_f64());
+                    error!("Due to -> {}", error);
+                    thread::sleep(delay);
+                    continue;
+                }
+            };
 
-            if connection.is_err() {
-                retry_time += 5;
-                error!("Failed to listen the websocket at {}, retrying in {}s ...", self.host, retry_time);
-                let error = connection.unwrap_err();
-                error!("Due to -> {}", error);
-                continue;
-            }
+            info!("Listening the logs of the Shelly {}", self.host);
+            attempt = 0;
 
-            let (mut socket, _) = connection?;
+            if let Some(connected) = &self.connected {
+                let _ = connected.send(());
+            }
 
             loop {
-                let read_msg = socket.read();
-                if read_msg.is_err() {
-                    error!("Failed to read the message");
+                let text = match socket.read() {
+                    Ok(Message::Text(text)) => text,
+                    Ok(Message::Close(frame)) => {
+                        warn!("The Shelly {} closed the websocket -> {}", self.host, frame.map_or_else(|| "no reason".to_string(), |frame| frame.to_string()));
+                        break;
+                    }
+                    //the pings are answered by tungstenite
+                    Ok(_) => continue,
+                    Err(error) => {
+                        warn!("Lost the connection to the websocket of the Shelly {} -> {}", self.host, error);
+                        break;
+                    }
+                };
+
+                match serde_json::from_str::<LogMessage>(&text) {
+                    Ok(log_msg) => {
+                        if self.print_message(&log_msg, &text) {
+                            printed += 1;
+                        }
+                    }
+                    Err(_) => {
+                        debug!("Failed to deserialize the message");
+                        debug!("raw message -> {}", text)
+                    }
                 }
 
-                let msg = read_msg.unwrap();
-                let text = msg.to_text()?;
-                let log_msg = serde_json::from_str::<LogMessage>(text);
+                if self.max_lines.is_some_and(|max_lines| printed >= max_lines) {
+                    debug!("{} messages printed, stopping the logger", printed);
+                    return Ok(());
+                }
+            }
 
-                if log_msg.is_err() {
-                    debug!("Failed to deserialize the message");
-                    debug!("raw message -> {}", text)
-                } else {
-                    let info_log_msg = log_msg?;
+            if !self.follow {
+                return Ok(());
+            }
 
-                    match info_log_msg.level {
-                        -1 => info!("{}", info_log_msg.data),
-                        _ => debug!("{}", info_log_msg.data),
-                    }
-                }
+            let delay = reconnect_delay(attempt);
+            attempt += 1;
+            info!("Reconnecting to the Shelly {} in {:.1}s ...", self.host, delay.as_secs_f64());
+            thread::sleep(delay);
+        }
+    }
+
+    ///Print a message of the Shelly with its time and its level, if its level is important enough
+    ///
+    /// The printed message is also saved in the [LogFile], when there is one
+    ///
+    /// * `message` - The [LogMessage] sent by the Shelly
+    /// * `raw` - The JSON message as received from the websocket
+    ///
+    ///Return [true] if the message has been printed
+    fn print_message(&self, message: &LogMessage, raw: &str) -> bool {
+        let level = DeviceLogLevel::from_shelly(message.level);
+        if level > self.level || !self.filter.matches(&message.data) {
+            return false;
+        }
+
+        let time = DateTime::from_timestamp_millis((message.ts * 1000.0) as i64)
+            .map(|time| time.with_timezone(&Local).format("%Y-%m-%d %H:%M:%S%.3f").to_string())
+            .unwrap_or_else(|| message.ts.to_string());
+        let label = format!("{:<5}", level);
+
+        eprintln!(
+            "[{} {} {}] {}",
+            time.dimmed(),
+            level.colorize(&label).bold(),
+            self.host,
+            level.colorize(message.data.trim_end()),
+        );
+
+        if let Some(log_file) = &self.log_file {
+            let text = format!("[{} {} {}] {}", time, label, self.host, message.data.trim_end());
+            if let Err(error) = log_file.borrow_mut().write(raw, &text) {
+                error!("Failed to save the message in the log file");
+                error!("Due to -> {}", error);
             }
         }
+
+        true
+    }
+}
+
+///Get the delay before the next connection to the websocket, doubled on each attempt and capped to [RECONNECT_MAX_DELAY]
+///
+/// A random jitter of up to [RECONNECT_JITTER] percent is added, like this several utilitaries don't reconnect all at the same time
+///
+/// * `attempt` - How many connections failed in a row
+fn reconnect_delay(attempt: u32) -> Duration {
+    let delay = RECONNECT_MIN_DELAY
+        .saturating_mul(2u64.saturating_pow(attempt))
+        .min(RECONNECT_MAX_DELAY);
+
+    //the hasher of the standard library is randomly seeded, enough for a jitter
+    let random = RandomState::new().hash_one(attempt);
+    let jitter = random % (delay * RECONNECT_JITTER / 100 + 1);
+
+    Duration::from_millis(delay + jitter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_filter_matches_the_prefix_of_the_firmware() {
+        let filter = LogFilter::new(Some(1), None, None).unwrap();
+
+        for text in ["script_1: hello", "script_1 hello", "script_1", "shelly_script_1: hello", "shelly_script.cpp:45 script_1 hello"] {
+            assert!(filter.matches(text), "{}", text);
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn script_filter_ignores_the_other_messages() {
+        let filter = LogFilter::new(Some(1), None, None).unwrap();
+
+        for text in ["script_12: hello", "script_2: script_1", "foo script_1: hello", "shelly_script.c:9 value 1 script_3", "sys: script 1 crashed"] {
+            assert!(!filter.matches(text), "{}", text);
+        }
+    }
+
+    #[test]
+    fn grep_and_exclude_filters_are_combined() {
+        let filter = LogFilter::new(None, Some("temp|hum"), Some("^debug")).unwrap();
+
+        assert!(filter.matches("temperature 21"));
+        assert!(!filter.matches("debug temperature 21"));
+        assert!(!filter.matches("pressure 1013"));
+        assert!(LogFilter::default().matches("anything"));
+    }
+
+    #[test]
+    fn invalid_regex_is_refused() {
+        assert!(LogFilter::new(None, Some("("), None).is_err());
+        assert!(LogFilter::new(None, None, Some("[")).is_err());
+    }
+}
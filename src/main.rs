@@ -3,12 +3,25 @@ mod service;
 mod logger;
 mod debugger;
 mod action;
+mod config;
+mod credentials;
+mod cli_error;
+mod output;
+mod log_file;
 
+use std::io::Write;
+use std::process::ExitCode;
 use clap::{Parser, Subcommand};
-use log::info;
+use log::{error, info};
+use crate::cli_error::{CliError, EXIT_CODES_HELP};
+use crate::config::{Config, ConfigFile};
+use crate::file_checker::{FileChecker, NamingRule, ScriptNaming};
+use crate::log_file::{LogFileFormat, LogRotation};
+use crate::logger::DeviceLogLevel;
+use crate::output::OutputFormat;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(author, version, about, long_about = None, after_help = EXIT_CODES_HELP)]
 struct Args {
     #[command(subcommand)]
     command: Commands,
@@ -17,17 +30,77 @@ struct Args {
     #[arg(long, default_value_t = String::from("info"))]
     log: String,
 
-    ///The IP of the host
-    #[arg(long, required = true)]
-    host: String,
+    ///The least important level of the logs of the Shelly that are printed, independent of --log ('error', 'warn', 'info', 'debug', 'verbose')
+    #[arg(long, value_enum, default_value_t = DeviceLogLevel::Info)]
+    device_log: DeviceLogLevel,
 
-    ///The username of the account on the Shelly
-    #[arg(long, default_value_t = String::from("admin"))]
-    username: String,
+    ///How the result of the command is printed ('table', 'json' or 'plain'), the colors are disabled when the output is not a terminal
+    #[arg(long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
 
-    ///The password used to connect to account on the Shelly
-    #[arg(long, required = true)]
-    password: String,
+    ///The project config file containing the device profiles (default: "./shelly.toml" if it exists)
+    #[arg(long)]
+    config: Option<String>,
+
+    ///The device profile of the config file to use (default: the "default_profile" of the config file)
+    #[arg(long)]
+    profile: Option<String>,
+
+    ///The IP of the host, required if not in the profile
+    #[arg(long)]
+    host: Option<String>,
+
+    ///The port of the HTTP API of the Shelly (default: 80)
+    #[arg(long)]
+    port: Option<u16>,
+
+    ///The username of the account on the Shelly (default: "admin")
+    #[arg(long)]
+    username: Option<String>,
+
+    ///The password used to connect to account on the Shelly, prefer the SHELLY_PASSWORD environment variable or the 'login' command (default: read from SHELLY_PASSWORD, the credential file or a prompt)
+    #[arg(long)]
+    password: Option<String>,
+}
+
+///The filters of the logs of the Shelly, shared by the commands printing them
+#[derive(clap::Args, Debug)]
+struct LogFilterArgs {
+    ///Only print the logs of this script
+    #[arg(long = "script")]
+    script_name: Option<String>,
+
+    ///Only print the logs matching this regex
+    #[arg(long)]
+    grep: Option<String>,
+
+    ///Don't print the logs matching this regex
+    #[arg(long)]
+    exclude: Option<String>,
+}
+
+///The file where the logs of the Shelly are saved, shared by the commands printing them
+#[derive(clap::Args, Debug)]
+struct LogFileArgs {
+    ///Also save the printed logs of the Shelly in this file, the logs are appended if it already exists
+    #[arg(long)]
+    log_file: Option<String>,
+
+    ///How the logs are written in the file ('text' like in the console, or 'json' for the raw messages of the Shelly)
+    #[arg(long, value_enum, default_value_t = LogFileFormat::Text)]
+    log_file_format: LogFileFormat,
+
+    ///When the log file is rotated ('size', 'daily' or 'never'), the previous file is renamed with a number suffix like "shelly.log.1"
+    #[arg(long, value_enum, default_value_t = LogRotation::Size)]
+    log_rotate: LogRotation,
+
+    ///The maximum size in MB of the log file when rotated by size
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u64).range(1..))]
+    log_max_size: u64,
+
+    ///How many rotated log files are kept, the older ones are deleted
+    #[arg(long, default_value_t = 5)]
+    log_keep: usize,
 }
 
 #[derive(Debug, Subcommand)]
@@ -39,19 +112,101 @@ enum Commands {
         vs_code: bool,
     },
 
+    ///Ask the password of the Shelly and save it in the credential file of the user config directory
+    Login {},
+
     ///Start the Shelly debugger
     Debug {
-        ///The directory where the utilitary will check for edited file
-        #[arg(long, default_value_t = String::from("./"))]
-        path: String,
+        ///The directory where the utilitary will check for edited file (default: "./")
+        #[arg(long)]
+        path: Option<String>,
 
-        ///The port that the websocket will use to get the logs on the Shelly (generaly you don't have to edit this one)
-        #[arg(long, default_value_t = 80)]
-        ws_port: i32,
+        ///The port that the websocket will use to get the logs on the Shelly, generaly you don't have to edit this one (default: 80)
+        #[arg(long)]
+        ws_port: Option<u16>,
 
         ///If indicated, directly run the script when is uploaded to the Shelly
         #[arg(short, long)]
         autorun: bool,
+
+        ///If indicated, don't run the script when it is uploaded, even if the profile has "autorun = true"
+        #[arg(long, conflicts_with = "autorun")]
+        no_autorun: bool,
+
+        ///If indicated, the uploaded scripts are enabled to run on the boot of the Shelly
+        #[arg(long)]
+        enable: bool,
+
+        ///If indicated, don't enable the uploaded scripts, even if the profile has "enable = true"
+        #[arg(long, conflicts_with = "enable")]
+        no_enable: bool,
+
+        ///The maximum size in bytes of each part of the code uploaded to the Shelly (the Shelly refuse too large requests)
+        #[arg(long, default_value_t = 1024, value_parser = clap::value_parser!(u32).range(1..))]
+        chunk_size: u32,
+
+        ///If indicated, delete the script from the Shelly when its file is removed from the directory
+        #[arg(long)]
+        delete: bool,
+
+        ///If indicated with --delete, only log the scripts that would be deleted from the Shelly
+        #[arg(long, requires = "delete")]
+        dry_run: bool,
+
+        ///If indicated, check the directory every 0.5 seconds instead of watching the file system events (useful on network file systems)
+        #[arg(long)]
+        poll: bool,
+
+        ///If indicated, the files different from their script on the Shelly are not uploaded on startup
+        #[arg(long)]
+        no_sync: bool,
+
+        ///If indicated, the files in the sub directories are checked too
+        #[arg(short, long)]
+        recursive: bool,
+
+        ///An extension of the files uploaded to the Shelly in addition to "js" (can be indicated multiple times)
+        #[arg(long = "extension")]
+        extensions: Vec<String>,
+
+        ///How the path of a file is mapped to the name of its script on the Shelly ('path': "lights/motion.js" -> "lights_motion", 'stem': "lights/motion.js" -> "motion")
+        #[arg(long, value_enum, default_value_t = NamingRule::Path)]
+        naming: NamingRule,
+
+        ///The separator placed between the folders in the name of the script when using the 'path' naming
+        #[arg(long, default_value_t = String::from("_"))]
+        name_separator: String,
+
+        #[command(flatten)]
+        log_filter: LogFilterArgs,
+
+        #[command(flatten)]
+        log_file: LogFileArgs,
+    },
+
+    ///Print the logs of the Shelly, without checking or uploading the files
+    Logs {
+        ///The port that the websocket will use to get the logs on the Shelly, generaly you don't have to edit this one (default: 80)
+        #[arg(long)]
+        ws_port: Option<u16>,
+
+        ///If indicated, exit when the connection to the Shelly is lost instead of reconnecting
+        #[arg(long)]
+        no_follow: bool,
+
+        ///Exit after printing this number of logs
+        #[arg(short = 'n', long)]
+        lines: Option<usize>,
+
+        ///Exit after this number of seconds
+        #[arg(long)]
+        duration: Option<u64>,
+
+        #[command(flatten)]
+        log_filter: LogFilterArgs,
+
+        #[command(flatten)]
+        log_file: LogFileArgs,
     },
 
     ///Run script in the Shelly by the name
@@ -64,11 +219,51 @@ enum Commands {
         script_name: String,
     },
 
+    ///Enable script in the Shelly by the name, an enabled script is run on the boot of the Shelly
+    Enable {
+        script_name: String,
+    },
+
+    ///Disable script in the Shelly by the name, a disabled script is not run on the boot of the Shelly
+    Disable {
+        script_name: String,
+    },
+
+    ///Evaluate a JavaScript expression in a running script on the Shelly and print its result
+    Eval {
+        script_name: String,
+
+        ///The expression to evaluate, it can use the variables and functions of the script
+        expression: String,
+    },
+
+    ///Start an interactive session evaluating each line in a running script on the Shelly ('exit' to quit)
+    Repl {
+        script_name: String,
+    },
+
+    ///Show the identity and the status of the Shelly, with the memory used by each script
+    Status {},
+
     ///Show the available script on the Shelly
-    List {}
+    List {},
+
+    ///Download the scripts from the Shelly into "<name>.js" files
+    Pull {
+        ///The name of the script to download, if not indicated all the scripts will be downloaded
+        script_name: Option<String>,
+
+        ///The directory where the files will be written (default: the scripts directory of the profile or "./")
+        #[arg(long)]
+        path: Option<String>,
+
+        ///If indicated, the existing files are overwritten, by default they are skipped to keep the local changes
+        #[arg(short, long)]
+        force: bool,
+    },
 }
 
-fn main() {
+fn main() -> ExitCode {
     let args = Args::parse();
 
     match args.log.to_lowercase().as_str() {
@@ -78,18 +273,53 @@ fn main() {
         "all" => std::env::set_var("RUST_LOG", "shelly_remote_helper"),
         other => {
             eprintln!("You can only specify 'info', 'error' or 'debug' into log, but {} is not a valid value", other);
-            return;
+            return CliError::BadArgs(other.to_string()).exit_code();
         }
     }
 
-    std::env::set_var("shelly-host", &args.host);
-    std::env::set_var("shelly-username", &args.username);
-    std::env::set_var("shelly-password", &args.password);
+    env_logger::Builder::from_default_env()
+        .format(|buf, record| {
+            writeln!(
+                buf,
+                "[{} {:<5} {}] {}",
+                buf.timestamp(),
+                buf.default_styled_level(record.level()),
+                record.target(),
+                credentials::redact(&record.args().to_string()),
+            )
+        })
+        .init();
 
-    env_logger::init();
+    output::init_colors(args.output);
 
-    info!("Shelly host ip: {}", &args.host);
-    info!("Shelly password: {}", &args.password);
+    match run(args) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(error) => {
+            error!("{}", error);
+            if let Some(hint) = error.hint() {
+                error!("{}", hint);
+            }
+            error.exit_code()
+        }
+    }
+}
+
+///Run the command indicated in the arguments
+///
+/// * `args` - The [Args] of the command line
+///
+///Return the [CliError] that made the command fail
+fn run(args: Args) -> Result<(), CliError> {
+    let profile = ConfigFile::load(args.config.as_deref())
+        .and_then(|config_file| config_file.profile(args.profile.as_deref()));
+    let mut config = profile
+        .and_then(|profile| Config::new(profile, args.host, args.port, args.username))
+        .map_err(|error| CliError::BadArgs(error.to_string()))?;
+    config.config_file = args.config;
+    config.password = args.password;
+    config.device_log_level = args.device_log;
+
+    info!("Shelly host ip: {}", &config.host);
     info!("Shelly Remote Helper have correctly started !");
 
     match args.command {
@@ -97,17 +327,80 @@ fn main() {
             path,
             ws_port,
             autorun,
-        } => debugger::debug(&path, ws_port, autorun),
+            no_autorun,
+            enable,
+            no_enable,
+            chunk_size,
+            delete,
+            dry_run,
+            poll,
+            no_sync,
+            recursive,
+            extensions,
+            naming,
+            name_separator,
+            log_filter,
+            log_file,
+        } => {
+            config.scripts_dir = path.unwrap_or(config.scripts_dir);
+            config.ws_port = ws_port.unwrap_or(config.ws_port);
+            config.autorun = (config.autorun || autorun) && !no_autorun;
+            config.enable = (config.enable || enable) && !no_enable;
+            config.chunk_size = chunk_size as usize;
+
+            let naming = ScriptNaming {
+                rule: naming,
+                separator: name_separator,
+            };
+            let file_checker = FileChecker::new(&config, delete, dry_run, recursive, &extensions, naming);
+            let log_filter = action::log_filter(&config, log_filter.script_name.as_deref(), log_filter.grep.as_deref(), log_filter.exclude.as_deref())?;
+            let log_file = action::log_file(log_file.log_file.as_deref(), log_file.log_file_format, log_file.log_rotate, log_file.log_max_size, log_file.log_keep)?;
+            debugger::debug(&config, poll, !no_sync, file_checker, log_filter, log_file)
+        },
+        Commands::Logs {
+            ws_port,
+            no_follow,
+            lines,
+            duration,
+            log_filter,
+            log_file,
+        } => {
+            config.ws_port = ws_port.unwrap_or(config.ws_port);
+
+            let log_filter = action::log_filter(&config, log_filter.script_name.as_deref(), log_filter.grep.as_deref(), log_filter.exclude.as_deref())?;
+            let log_file = action::log_file(log_file.log_file.as_deref(), log_file.log_file_format, log_file.log_rotate, log_file.log_max_size, log_file.log_keep)?;
+            action::logs(&config, log_filter, log_file, !no_follow, lines, duration)
+        },
         Commands::Start {
             script_name
-        } => action::start(&script_name),
+        } => action::start(&config, &script_name, args.output),
         Commands::Stop {
             script_name
-        } => action::stop(&script_name),
+        } => action::stop(&config, &script_name, args.output),
+        Commands::Enable {
+            script_name
+        } => action::set_enable(&config, &script_name, true, args.output),
+        Commands::Disable {
+            script_name
+        } => action::set_enable(&config, &script_name, false, args.output),
+        Commands::Eval {
+            script_name,
+            expression,
+        } => action::eval(&config, &script_name, &expression, args.output),
+        Commands::Repl {
+            script_name
+        } => action::repl(&config, &script_name),
         Commands::Setup {
             vs_code,
-        } => action::setup(vs_code),
-        Commands::List {} => action::list(),
+        } => action::setup(&config, vs_code),
+        Commands::List {} => action::list(&config, args.output),
+        Commands::Status {} => action::status(&config, args.output),
+        Commands::Login {} => action::login(&config),
+        Commands::Pull {
+            script_name,
+            path,
+            force,
+        } => action::pull(&config, script_name.as_deref(), path.as_deref().unwrap_or(&config.scripts_dir), force),
     }
 }
 
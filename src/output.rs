@@ -0,0 +1,201 @@
+use std::io::IsTerminal;
+use clap::ValueEnum;
+use serde::Serialize;
+use crate::cli_error::CliError;
+use crate::config::Config;
+use crate::service::shelly_rest_api::{DeviceInfo, Script, ScriptStatus, ScriptingLimits, SysStatus};
+
+///How the result of a command is printed on the standard output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    ///A table, colored when printed in a terminal
+    Table,
+    ///A JSON document, to be read by other programs
+    Json,
+    ///One line per element with the values separated by tabs, without colors
+    Plain,
+}
+
+///Enable the colors only when the output is a table printed in a terminal
+///
+/// * `format` - The [OutputFormat] of the command
+pub fn init_colors(format: OutputFormat) {
+    if format != OutputFormat::Table || !std::io::stdout().is_terminal() {
+        colored::control::set_override(false);
+    }
+}
+
+///Print the value as a pretty JSON document on the standard output
+///
+/// * `value` - The value to print
+pub fn print_json<T: Serialize>(value: &T) -> Result<(), CliError> {
+    let json = serde_json::to_string_pretty(value).map_err(|error| CliError::Other(Box::new(error)))?;
+    println!("{}", json);
+
+    Ok(())
+}
+
+///Pretty print the result of an evaluated expression, the result is indented when it is a JSON value
+///
+/// * `result` - The result sent by the Shelly
+pub fn format_eval_result(result: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(result) {
+        Ok(value @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => serde_json::to_string_pretty(&value).unwrap_or_else(|_| result.to_string()),
+        _ => result.to_string(),
+    }
+}
+
+///Format a size in bytes in a human readable way, like `12.5 KB`
+///
+/// * `bytes` - The size in bytes
+pub fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1048576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1048576.0),
+    }
+}
+
+///Format a duration in seconds in a human readable way, like `1d 02:03:04`
+///
+/// * `seconds` - The duration in seconds
+pub fn format_duration(seconds: u64) -> String {
+    let days = seconds / 86400;
+    let time = format!("{:02}:{:02}:{:02}", seconds % 86400 / 3600, seconds % 3600 / 60, seconds % 60);
+
+    if days > 0 {
+        format!("{}d {}", days, time)
+    } else {
+        time
+    }
+}
+
+///The Shelly targeted by a command, as printed in the JSON documents
+#[derive(Serialize, Debug)]
+pub struct DeviceReport {
+    ///The IP of the Shelly
+    pub host: String,
+    ///The port of the HTTP API of the Shelly
+    pub port: u16,
+}
+
+impl DeviceReport {
+    ///Create the report of the Shelly indicated in the [Config]
+    ///
+    /// * `config` - The [Config] used to communicate with the Shelly
+    pub fn new(config: &Config) -> Self {
+        DeviceReport {
+            host: config.host.clone(),
+            port: config.port,
+        }
+    }
+}
+
+///The JSON document printed by the `list` command
+#[derive(Serialize, Debug)]
+pub struct ScriptListReport<'a> {
+    ///The Shelly where the scripts are
+    pub device: DeviceReport,
+    ///The scripts on the Shelly with their status
+    pub scripts: &'a [ScriptReport],
+}
+
+///The JSON document printed by the `start` and `stop` commands
+#[derive(Serialize, Debug)]
+pub struct ScriptActionReport<'a> {
+    ///The Shelly where the script is
+    pub device: DeviceReport,
+    ///The action done on the script, `start` or `stop`
+    pub action: &'static str,
+    ///The id of the script on the Shelly
+    pub id: i32,
+    ///The name of the script on the Shelly
+    pub name: &'a str,
+    ///If the script is running after the action
+    pub running: bool,
+}
+
+///The JSON document printed by the `enable` and `disable` commands
+#[derive(Serialize, Debug)]
+pub struct ScriptEnableReport<'a> {
+    ///The Shelly where the script is
+    pub device: DeviceReport,
+    ///The id of the script on the Shelly
+    pub id: i32,
+    ///The name of the script on the Shelly
+    pub name: &'a str,
+    ///If the script is enabled after the action
+    pub enable: bool,
+}
+
+///The JSON document printed by the `eval` command
+#[derive(Serialize, Debug)]
+pub struct EvalReport<'a> {
+    ///The Shelly where the script is
+    pub device: DeviceReport,
+    ///The id of the script on the Shelly
+    pub id: i32,
+    ///The name of the script on the Shelly
+    pub name: &'a str,
+    ///The evaluated expression
+    pub expression: &'a str,
+    ///The result of the expression, as a JSON value when it can be parsed or as a string
+    pub result: serde_json::Value,
+}
+
+///A script with its status, as printed by the `list` and `status` commands
+#[derive(Serialize, Debug)]
+pub struct ScriptReport {
+    ///The id of the script on the Shelly
+    pub id: i32,
+    ///The name of the script on the Shelly
+    pub name: String,
+    ///If the script is run on the boot of the Shelly, [None] if not indicated by the Shelly
+    pub enable: Option<bool>,
+    ///If the script is currently running, [None] if not indicated by the Shelly
+    pub running: Option<bool>,
+    ///The memory in bytes currently used by the script, only indicated while it is running
+    pub mem_used: Option<u64>,
+    ///The maximum memory in bytes used by the script since it started
+    pub mem_peak: Option<u64>,
+    ///The memory in bytes still available for the script
+    pub mem_free: Option<u64>,
+    ///The last errors of the script, like `crashed` or `out_of_memory`
+    pub errors: Vec<String>,
+}
+
+impl ScriptReport {
+    ///Create the report of a script
+    ///
+    /// * `script` - The [Script] from the list of the Shelly
+    /// * `status` - The [ScriptStatus] of the script, if it has been read
+    pub fn new(script: Script, status: Option<ScriptStatus>) -> Self {
+        let status = status.unwrap_or_default();
+
+        ScriptReport {
+            id: script.id,
+            name: script.name,
+            enable: script.enable,
+            running: script.running,
+            mem_used: status.mem_used,
+            mem_peak: status.mem_peak,
+            mem_free: status.mem_free,
+            errors: status.errors,
+        }
+    }
+}
+
+///The JSON document printed by the `status` command
+#[derive(Serialize, Debug)]
+pub struct StatusReport<'a> {
+    ///The Shelly targeted by the command
+    pub device: DeviceReport,
+    ///The identity of the Shelly
+    pub info: &'a DeviceInfo,
+    ///The status of the system of the Shelly
+    pub system: &'a SysStatus,
+    ///The limits of the scripting on the Shelly, [None] if the scripting is not supported
+    pub limits: Option<&'a ScriptingLimits>,
+    ///The scripts on the Shelly with their status
+    pub scripts: &'a [ScriptReport],
+}
@@ -1,12 +1,19 @@
 use std::error::Error;
-use std::ffi::OsStr;
 use std::fmt::{Display, Formatter};
 use std::fs::read_to_string;
-use std::path::Path;
-use log::{debug, error, info};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::thread;
+use std::time::Duration;
+use log::{debug, error, info, warn};
+use reqwest::blocking::Response;
 use reqwest::header::{HeaderValue};
+use reqwest::StatusCode;
+use serde::de::{DeserializeOwned, IgnoredAny};
 use serde::{Deserialize, Serialize};
 use diqwest::blocking::WithDigestAuth;
+use crate::config::Config;
+use crate::credentials;
 
 ///JavaScript function that can be called in the js code to stop the current file
 ///
@@ -24,48 +31,82 @@ function stopCurrentScript() {
 };
 ";
 
+///The default size in bytes of a [Chunk], the Shelly refuse the requests with a too large body
+pub const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+///How many times the code will be uploaded before giving up
+const UPLOAD_MAX_ATTEMPT: u32 = 3;
+
+///The delay in milliseconds before uploading again the code after a [Chunk] failed
+const UPLOAD_RETRY_DELAY: u64 = 500;
+
+///The delay in seconds before a request without answer from the Shelly is cancelled
+const REQUEST_TIMEOUT: u64 = 10;
+
+///Check that the name can be sent to the Shelly as the name of a script
+///
+/// Only the names that can't be valid are refused here, the Shelly answers with an error for the other names it doesn't accept
+///
+/// * `script_name` - The name of the script
+///
+///Return [ShellyError::InvalidScriptName] with the broken rule if the name is not valid
+pub fn validate_script_name(script_name: &str) -> Result<(), ShellyError> {
+    let reason = if script_name.is_empty() {
+        "the name is empty".to_string()
+    } else if let Some(invalid) = script_name.chars().find(|c| c.is_control()) {
+        format!("the name contains the control character {:?}", invalid)
+    } else {
+        return Ok(());
+    };
+
+    Err(ShellyError::InvalidScriptName {
+        name: script_name.to_string(),
+        reason,
+    })
+}
+
+///The delay in seconds before the detection of the Shelly with [Shelly::new] is cancelled
+const PROBE_TIMEOUT: u64 = 3;
+
+///The first generation of Shelly with the RPC API and the scripting
+const FIRST_RPC_GENERATION: u8 = 2;
+
 ///Upload and save file to the Shelly by the file path
 ///
-/// * `save_script_to_shelly` - the path of the file to upload to the shelly
-pub fn save_script_to_shelly(file_path: &str) -> Result<(), Box<dyn Error>>{
+/// * `shelly` - the Shelly where the file is uploaded
+/// * `file_path` - the path of the file to upload to the shelly
+/// * `file_name` - the name of the script on the Shelly
+pub fn save_script_to_shelly(shelly: &Shelly, file_path: &str, file_name: &str) -> Result<(), Box<dyn Error>>{
+    validate_script_name(file_name)?;
     let file_content = read_to_string(file_path)?;
-    let file_name = Path::new(file_path).file_name()
-        .ok_or(OsStr::new("/"))
-        .unwrap()
-        .to_str()
-        .ok_or("What's the name of this file guys ?")?
-        .replace(".js", "");
-
-    if !file_path.contains(".js") {
-        return Ok(());
-    }
 
     debug!("file name : {file_name}");
 
-    let shelly = Shelly::new()?;
     let available_scripts = shelly.script_list()?;
     debug!("{:?}", available_scripts);
 
     let script = available_scripts
         .iter()
-        .filter_map(|script| {
-            if script.name == file_name {
-                Some(script)
-            } else {
-                None
-            }
-        }).next();
+        .find(|script| script.name == file_name);
     debug!("{:?}", script);
 
-    if script.is_none() {
-        let new_script = shelly.script_create(&file_name)?;
-        shelly.script_put_code(&new_script, file_content, false)?;
-        shelly.script_start(&new_script)?;
-    } else {
-        let script_u = script.unwrap();
-        shelly.script_stop(script_u)?;
-        shelly.script_put_code(script_u, file_content, false)?;
-        shelly.script_start(script_u)?;
+    match script {
+        None => {
+            let new_script = shelly.script_create(file_name)?;
+            shelly.script_put_code(&new_script, file_content)?;
+            if shelly.enable {
+                shelly.script_set_enable(&new_script, true)?;
+            }
+            shelly.script_start(&new_script)?;
+        }
+        Some(script_u) => {
+            shelly.script_stop(script_u)?;
+            shelly.script_put_code(script_u, file_content)?;
+            if shelly.enable && script_u.enable != Some(true) {
+                shelly.script_set_enable(script_u, true)?;
+            }
+            shelly.script_start(script_u)?;
+        }
     }
 
     info!("The file has been correctly uploaded !");
@@ -73,6 +114,73 @@ pub fn save_script_to_shelly(file_path: &str) -> Result<(), Box<dyn Error>>{
     Ok(())
 }
 
+///Delete the script of a removed file from the Shelly by the script name
+///
+/// * `shelly` - the Shelly where the script is deleted
+/// * `file_name` - the name of the script on the Shelly
+pub fn delete_script_from_shelly(shelly: &Shelly, file_name: &str) -> Result<(), Box<dyn Error>>{
+    debug!("file name : {file_name}");
+
+    let available_scripts = shelly.script_list()?;
+
+    match available_scripts.iter().find(|script| script.name == file_name) {
+        None => debug!("{} is not on the Shelly, nothing to delete", file_name),
+        Some(script) => {
+            shelly.script_stop(script)?;
+            shelly.script_delete(script)?;
+            info!("The script {} has been deleted from the Shelly !", file_name);
+        }
+    }
+
+    Ok(())
+}
+
+///Ask the Shelly to describe itself, no authentication is required
+///
+/// * `client` - The Http client used to communicate with the Shelly
+/// * `host` - The IP of the Shelly
+/// * `port` - The port of the HTTP API of the Shelly
+///
+///Equivalent to http://{shelly_ip}/shelly
+fn probe_device(client: &reqwest::blocking::Client, host: &str, port: u16) -> Result<DeviceProbe, ShellyError> {
+    let response = client
+        .get(format!("http://{}:{}/shelly", host, port))
+        .timeout(Duration::from_secs(PROBE_TIMEOUT))
+        .send()?;
+
+    let body = check_response(response)?.text()?;
+
+    Ok(serde_json::from_str(&body)?)
+}
+
+///Split the code in parts of maximum `chunk_size` bytes, without cutting a character in half
+///
+/// A part is extended to the end of a character cut by the `chunk_size`, an empty code gives one empty part
+///
+/// * `code` - The code to split
+/// * `chunk_size` - The maximum size in bytes of a part
+fn split_in_chunks(code: &str, chunk_size: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = code;
+
+    while !rest.is_empty() {
+        let mut end = chunk_size.clamp(1, rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+
+        let (chunk, next) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = next;
+    }
+
+    if chunks.is_empty() {
+        chunks.push(rest);
+    }
+
+    chunks
+}
+
 ///Representation of a script on the Shelly
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Script {
@@ -86,6 +194,143 @@ pub struct Script {
     pub running: Option<bool>,
 }
 
+///The status of a script on the Shelly, as answered by `Script.GetStatus`
+///
+/// The memory is only indicated while the script is running
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct ScriptStatus {
+    ///The id of the script on the Shelly
+    pub id: i32,
+    ///If the script is currently running
+    #[serde(default)]
+    pub running: bool,
+    ///The memory in bytes currently used by the script
+    pub mem_used: Option<u64>,
+    ///The maximum memory in bytes used by the script since it started
+    pub mem_peak: Option<u64>,
+    ///The memory in bytes still available for the script
+    pub mem_free: Option<u64>,
+    ///The last errors of the script, like `crashed` or `out_of_memory`
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+///The identity of the Shelly, as answered by `Shelly.GetDeviceInfo`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeviceInfo {
+    ///The name of the Shelly given by the user
+    pub name: Option<String>,
+    ///The id of the Shelly, like `shellyplus1-a8032ab12345`
+    pub id: String,
+    ///The MAC address of the Shelly
+    pub mac: String,
+    ///The model of the Shelly
+    pub model: String,
+    ///The generation of the Shelly
+    #[serde(rename = "gen")]
+    pub generation: u8,
+    ///The id of the firmware
+    pub fw_id: String,
+    ///The version of the firmware
+    pub ver: String,
+    ///The application of the firmware
+    pub app: String,
+    ///If the authentication is enabled
+    #[serde(default)]
+    pub auth_en: bool,
+}
+
+///The status of the system of the Shelly, as answered by `Sys.GetStatus`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct SysStatus {
+    ///The time in seconds since the last boot
+    pub uptime: u64,
+    ///The total RAM in bytes
+    pub ram_size: u64,
+    ///The free RAM in bytes
+    pub ram_free: u64,
+    ///The total size in bytes of the file system
+    pub fs_size: u64,
+    ///The free space in bytes of the file system
+    pub fs_free: u64,
+    ///If the Shelly has to be restarted to apply a configuration
+    #[serde(default)]
+    pub restart_required: bool,
+}
+
+///The description of the Shelly sent by http://{shelly_ip}/shelly, its fields depend on the generation
+///
+/// The Gen1 devices don't indicate their generation and use `auth`, the newer ones use `gen` and `auth_en`
+#[derive(Deserialize, Debug, Default)]
+struct DeviceProbe {
+    ///The generation of the Shelly, missing on the Gen1 devices
+    #[serde(rename = "gen")]
+    generation: Option<u8>,
+    ///If the authentication is enabled on the Gen2 devices and newer
+    auth_en: Option<bool>,
+    ///If the authentication is enabled on the Gen1 devices
+    auth: Option<bool>,
+    ///The model of the Gen1 devices
+    #[serde(rename = "type")]
+    model: Option<String>,
+    ///The MAC address of the Shelly
+    mac: Option<String>,
+    ///The firmware of the Gen1 devices
+    fw: Option<String>,
+}
+
+impl DeviceProbe {
+    ///The generation of the Shelly, the Gen1 devices are recognized by their `type`, a Shelly that can't be detected is considered as a Gen2 device
+    fn generation(&self) -> u8 {
+        match self {
+            DeviceProbe { generation: Some(generation), .. } => *generation,
+            DeviceProbe { model: Some(_), .. } => 1,
+            _ => FIRST_RPC_GENERATION,
+        }
+    }
+
+    ///If the Shelly requires the username and the password, [true] when it can't be detected
+    fn auth_enabled(&self) -> bool {
+        self.auth_en.or(self.auth).unwrap_or(true)
+    }
+}
+
+///The status of a Gen1 Shelly sent by http://{shelly_ip}/status
+#[derive(Deserialize, Debug)]
+struct Gen1Status {
+    ///The time in seconds since the last boot
+    uptime: u64,
+    ///The total RAM in bytes
+    ram_total: u64,
+    ///The free RAM in bytes
+    ram_free: u64,
+    ///The total size in bytes of the file system
+    fs_size: u64,
+    ///The free space in bytes of the file system
+    fs_free: u64,
+}
+
+///The limits of the scripting on the Shelly, documented by Shelly because the device doesn't report them
+#[derive(Serialize, Debug)]
+pub struct ScriptingLimits {
+    ///The maximum number of scripts on the Shelly
+    pub scripts: u32,
+    ///The maximum number of timers used at the same time by a script
+    pub timers: u32,
+    ///The maximum number of event and status subscriptions of a script
+    pub subscriptions: u32,
+    ///The maximum number of RPC calls running at the same time in a script
+    pub rpc_calls: u32,
+}
+
+///The scripting limits of the Gen2 and newer Shelly
+pub const SCRIPTING_LIMITS: ScriptingLimits = ScriptingLimits {
+    scripts: 10,
+    timers: 5,
+    subscriptions: 5,
+    rpc_calls: 5,
+};
+
 ///A chunk is a part of the code that will be send to the Shelly
 #[derive(Serialize, Deserialize)]
 struct Chunk {
@@ -97,6 +342,98 @@ struct Chunk {
     append: bool,
 }
 
+///The id of the script targeted by a request
+#[derive(Serialize, Deserialize)]
+struct ScriptId {
+    ///The id of the script on the Shelly
+    id: i32,
+}
+
+///The name of the script created on the Shelly
+#[derive(Serialize)]
+struct ScriptName<'a> {
+    ///The name of the script
+    name: &'a str,
+}
+
+///The configuration of a script changed on the Shelly
+#[derive(Serialize)]
+struct ScriptConfigRequest {
+    ///The id of the script on the Shelly
+    id: i32,
+    ///The new configuration of the script
+    config: ScriptConfig,
+}
+
+///The configuration of a script on the Shelly
+#[derive(Serialize)]
+struct ScriptConfig {
+    ///If the script is run on the boot of the Shelly
+    enable: bool,
+}
+
+///The code evaluated in a running script
+#[derive(Serialize)]
+struct EvalRequest<'a> {
+    ///The id of the script on the Shelly
+    id: i32,
+    ///The JavaScript code to evaluate
+    code: &'a str,
+}
+
+///The result of the code evaluated in a script, as answered by `Script.Eval`
+#[derive(Deserialize)]
+struct EvalResponse {
+    ///The result of the code converted to a string
+    result: String,
+}
+
+///The scripts on the Shelly, as answered by `Script.List`
+#[derive(Deserialize)]
+struct ScriptList {
+    ///The scripts with their current status
+    scripts: Vec<Script>,
+}
+
+///A JSON-RPC frame sent to the Shelly
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    ///The id of the request, sent back by the Shelly in the answer
+    id: u64,
+    ///The name of the called method
+    method: &'a str,
+    ///The parameters of the method, not sent if there is none
+    #[serde(skip_serializing_if = "serde_json::Value::is_null")]
+    params: serde_json::Value,
+}
+
+///A JSON-RPC frame answered by the Shelly, with either a `result` or an `error`
+#[derive(Deserialize)]
+struct RpcResponse {
+    ///The result of the method, can be missing when the method doesn't return anything
+    result: Option<serde_json::Value>,
+    ///The error returned by the method
+    error: Option<RpcError>,
+}
+
+///The part of the code of a script requested to the Shelly
+#[derive(Serialize, Deserialize)]
+struct CodeRequest {
+    ///The script where the code have to be read
+    id: i32,
+    ///The position in the code where the Shelly will start to read
+    offset: usize,
+}
+
+///The part of the code of a script sent by the Shelly
+#[derive(Serialize, Deserialize)]
+struct CodeResponse {
+    ///The code that has been read
+    data: String,
+    ///How many bytes of code are left to read after this part
+    left: usize,
+}
+
 ///Define the Shelly structure and data, can create an instance of it with [Shelly::new()]
 #[derive(Debug)]
 pub struct Shelly {
@@ -104,77 +441,159 @@ pub struct Shelly {
     client: reqwest::blocking::Client,
     ///The IP of the Shelly
     host: String,
+    ///The port of the HTTP API of the Shelly
+    port: u16,
     ///The username to be able to connect to the Shelly
     username: String,
-    ///The password to be able to connect to the Shelly
-    password: String,
+    ///The password indicated in the command line, the other sources are only read when the Shelly requires it
+    cli_password: Option<String>,
+    ///The password to be able to connect to the Shelly, found on the first request that requires it
+    password: OnceLock<String>,
+    ///If [true], a script already running is restarted by [Shelly::script_start]
+    autorun: bool,
+    ///If [true], the uploaded scripts are enabled to run on the boot of the Shelly
+    enable: bool,
+    ///The maximum size in bytes of the code sent in one [Chunk]
+    chunk_size: usize,
+    ///The id of the next JSON-RPC request sent by [Shelly::call]
+    request_id: AtomicU64,
+    ///What the Shelly said about itself when it has been detected by [Shelly::new]
+    probe: DeviceProbe,
 }
 
 impl Shelly {
-    ///Create a new instance of Shelly
+    ///Create a new instance of Shelly, its generation and if the authentication is enabled are detected with http://{shelly_ip}/shelly
+    ///
+    /// If the Shelly can't be detected, it is considered as a Gen2 device with the authentication enabled
+    ///
+    /// * `config` - The [Config] used to communicate with the Shelly
+    pub fn new(config: &Config) -> Self {
+        let client = reqwest::blocking::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT))
+            .build()
+            .unwrap_or_default();
+
+        let probe = match probe_device(&client, &config.host, config.port) {
+            Ok(probe) => {
+                debug!("Shelly detected -> {:?}", probe);
+                probe
+            }
+            Err(error) => {
+                debug!("Unable to detect the Shelly, it is considered as a Gen2 device -> {}", error);
+                DeviceProbe::default()
+            }
+        };
+
+        Shelly {
+            client,
+            host: config.host.clone(),
+            port: config.port,
+            username: config.username.clone(),
+            cli_password: config.password.clone(),
+            password: OnceLock::new(),
+            autorun: config.autorun,
+            enable: config.enable,
+            chunk_size: config.chunk_size,
+            request_id: AtomicU64::new(1),
+            probe,
+        }
+    }
+
+    ///The generation of the Shelly, a Shelly that can't be detected is considered as a Gen2 device
+    pub fn generation(&self) -> u8 {
+        self.probe.generation()
+    }
+
+    ///If the Shelly has the RPC API and the scripting, [true] for the Gen2 devices and newer
+    pub fn supports_scripting(&self) -> bool {
+        self.generation() >= FIRST_RPC_GENERATION
+    }
+
+    ///Get the password of the Shelly, it is searched with [credentials::find_password] on the first call
+    ///
+    ///Return [ShellyError::MissingPassword] if no password has been found
+    fn password(&self) -> Result<&str, ShellyError> {
+        if let Some(password) = self.password.get() {
+            return Ok(password);
+        }
+
+        let password = credentials::find_password(self.cli_password.clone(), &self.host)
+            .map_err(|error| ShellyError::MissingPassword(error.to_string()))?;
+
+        Ok(self.password.get_or_init(|| password))
+    }
+
+    ///Send a GET request to the REST API of a Gen1 Shelly, which uses the basic authentication
     ///
-    /// Return an error if something goes wrong
-    pub fn new() -> Result<Self, Box<dyn Error>>{
-        Ok(Shelly {
-                client: reqwest::blocking::Client::new(),
-                host: std::env::var("shelly-host")?,
-                username: std::env::var("shelly-username")?,
-                password: std::env::var("shelly-password")?,
-            })
+    /// * `uri` - The uri of the endpoint, like `/status`
+    fn get_gen1<R: DeserializeOwned>(&self, uri: &str) -> Result<R, ShellyError> {
+        let mut request = self.client.get(self.get_url(uri));
+        if self.probe.auth_enabled() {
+            request = request.basic_auth(&self.username, Some(self.password()?));
+        }
+
+        let body = check_response(request.send()?)?.text()?;
+        debug!("{} <- {}", uri, body);
+
+        Ok(serde_json::from_str(&body)?)
     }
 
     ///Generate the url to access the Shelly API
     fn get_url(&self, uri: &str) -> String {
-        format!("http://{}{uri}", self.host)
+        format!("http://{}:{}{uri}", self.host, self.port)
     }
 
-    ///Create and upload the script to the shelly
+    ///Call a method of the RPC API of the Shelly
     ///
-    /// * `script_name` - The name of the script that will be upload
+    /// The request is sent as a JSON-RPC frame to http://{shelly_ip}/rpc, the `result` of the answer is
+    /// deserialized in `R` and its `error` is returned as a [ShellyError::Rpc]
     ///
-    ///Equivalent to http://{shelly_ip}/rpc/Script.Create
+    /// * `method` - The name of the method, like `Script.List`
+    /// * `params` - The parameters of the method, serialized in JSON
     ///
-    ///Return an error if something goes wrong
-    fn script_create(&self, script_name: &str) -> Result<Script, Box<dyn Error>> {
-        let uri = "/rpc/Script.Create";
-        let url = self.get_url(uri);
+    ///Return an Error if something goes wrong
+    pub fn call<P: Serialize, R: DeserializeOwned>(&self, method: &str, params: &P) -> Result<R, ShellyError> {
+        if !self.supports_scripting() {
+            return Err(ShellyError::UnsupportedGeneration(self.generation()));
+        }
 
-        let json = format!("{{ \"name\": \"{}\" }}", script_name);
+        let url = self.get_url("/rpc");
+        let request = RpcRequest {
+            id: self.request_id.fetch_add(1, Ordering::Relaxed),
+            method,
+            params: serde_json::to_value(params)?,
+        };
+        let json = serde_json::to_string(&request)?;
+        debug!("{} -> {}", url, json);
 
-        let response = self.client
+        let request = self.client
             .post(&url)
             .header("Content-Length", HeaderValue::from(json.len()))
-            .body(json)
-            .send_with_digest_auth(&self.username, &self.password)?;
-
-        if response.status().is_client_error() {
-            let error_code = response.status().as_u16();
-            let body = response.text()?;
-            error!("{}", &body);
-
-            return Err(Box::new(ClientRequestError { code: error_code }));
-        }
-        else if response.status().is_server_error() {
-            let id = response.status().as_u16().clone();
-            let msg = response.text();
+            .body(json);
+        let response = if self.probe.auth_enabled() {
+            request.send_with_digest_auth(&self.username, self.password()?)?
+        } else {
+            request.send()?
+        };
 
-            let mut checked_msg = None;
-            if msg.is_ok() {
-                checked_msg = Some(msg.unwrap())
-            }
+        let body = check_response(response)?.text()?;
+        debug!("{} <- {}", method, body);
 
-            return Err(Box::new(InternalServerError {
-                code: id,
-                msg: checked_msg,
-            }));
-        }
+        decode_result(&body)
+    }
 
-        let body = response.text()?;
-        debug!("{body}");
-        let id: serde_json::Value = serde_json::from_str(&body)?;
+    ///Create and upload the script to the shelly
+    ///
+    /// * `script_name` - The name of the script that will be upload
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.Create
+    ///
+    ///Return an error if something goes wrong
+    fn script_create(&self, script_name: &str) -> Result<Script, ShellyError> {
+        let created: ScriptId = self.call("Script.Create", &ScriptName { name: script_name })?;
 
         Ok(Script {
-            id: id["id"].as_i64().ok_or(-1).unwrap() as i32,
+            id: created.id,
             name: script_name.to_string(),
             enable: None,
             running: Some(false),
@@ -183,93 +602,115 @@ impl Shelly {
 
     ///Will override all the code in the indicate Script
     ///
+    /// The code is split in [Chunk] of `chunk_size` bytes, because the Shelly refuse the requests with a too large body,
+    /// the first [Chunk] replace the code and all the next ones are appended to it
+    ///
+    /// When a [Chunk] fails the whole upload is restarted from the first [Chunk], because appending is not idempotent:
+    /// the Shelly may have saved the [Chunk] even if its response has been lost
+    ///
     /// * `script` - The script to send the code
     /// * `data` - The code that will be upload to the Shelly
-    /// * `append` - If [true] append the code at the end of the file, If [false] will override all existing code
     ///
     ///Equivalent to http://{shelly_ip}/rpc/Script.PutCode
     ///
     /// Return an Error if something goes wrong
-    fn script_put_code(&self, script: &Script, data: String, append: bool) -> Result<(), Box<dyn Error>>{
-        let uri = "/rpc/Script.PutCode";
-        let url = self.get_url(uri);
-        debug!("{}", url);
+    fn script_put_code(&self, script: &Script, data: String) -> Result<(), ShellyError>{
+        let code = format!("{data}{JS_STOP_FUNCTION}");
+        let chunks = split_in_chunks(&code, self.chunk_size);
+
+        let mut attempt = 1;
+        while let Err(error) = self.script_put_chunks(script, &chunks) {
+            if attempt >= UPLOAD_MAX_ATTEMPT {
+                error!("Failed to upload {} after {} attempts", script.name, attempt);
+                return Err(error);
+            }
 
-        let chunk = Chunk {
-            id: script.id,
-            code: format!("{data}{JS_STOP_FUNCTION}"),
-            append: append,
-        };
-        let json = serde_json::to_string(&chunk)?;
+            warn!("Failed to upload {}, restarting the upload ... -> {}", script.name, error);
+            attempt += 1;
+            thread::sleep(Duration::from_millis(UPLOAD_RETRY_DELAY));
+        }
 
-        let response = self.client
-            .post(&url)
-            .header("Content-Length", HeaderValue::from(json.len()))
-            .body(json)
-            .send_with_digest_auth(&self.username, &self.password)?;
+        Ok(())
+    }
 
+    ///Send all the parts of the code to the Shelly, the first one replace the code of the script
+    ///
+    /// * `script` - The script to send the code
+    /// * `chunks` - The parts of the code, see [split_in_chunks]
+    ///
+    /// Return an Error as soon as a [Chunk] fails
+    fn script_put_chunks(&self, script: &Script, chunks: &[&str]) -> Result<(), ShellyError>{
+        let total = chunks.len();
 
-        if response.status().is_client_error() {
-            return Err(Box::new(ClientRequestError { code: response.status().as_u16() }));
-        }
-        else if response.status().is_server_error() {
-            let id = response.status().as_u16().clone();
-            let msg = response.text();
+        for (index, code) in chunks.iter().enumerate() {
+            let chunk = Chunk {
+                id: script.id,
+                code: code.to_string(),
+                append: index > 0,
+            };
 
-            let mut checked_msg = None;
-            if msg.is_ok() {
-                checked_msg = Some(msg.unwrap())
-            }
+            self.script_put_chunk(&chunk)
+                .inspect_err(|_| warn!("Failed to upload the chunk {}/{} of {}", index + 1, total, script.name))?;
 
-            return Err(Box::new(InternalServerError {
-                code: id,
-                msg: checked_msg,
-            }));
+            info!("Uploading {} : chunk {}/{} sent", script.name, index + 1, total);
         }
 
         Ok(())
     }
 
-    ///Will return a list of the currents `Vec<Script>` with their current status
+    ///Send one [Chunk] of code to the Shelly
     ///
-    ///Return an Error if something goes wrong
-    pub fn script_list(&self) -> Result<Vec<Script>, Box<dyn Error>> {
-        let uri = "/rpc/Script.List";
-        let url = self.get_url(uri);
+    /// * `chunk` - The [Chunk] to send
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.PutCode
+    ///
+    /// Return an Error if something goes wrong
+    fn script_put_chunk(&self, chunk: &Chunk) -> Result<(), ShellyError>{
+        let _: IgnoredAny = self.call("Script.PutCode", chunk)?;
 
-        let response = self.client
-            .get(&url)
-            .send_with_digest_auth(&self.username, &self.password)?;
+        Ok(())
+    }
 
-        if response.status().is_client_error() {
-            return Err(Box::new(ClientRequestError { code: response.status().as_u16() }));
-        }
-        else if response.status().is_server_error() {
-            let id = response.status().as_u16().clone();
-            let msg = response.text();
+    ///Will download all the code of the indicate Script, the code is sent by the Shelly in multiple parts
+    ///
+    /// The [JS_STOP_FUNCTION] appended on upload is removed from the code
+    ///
+    /// * `script` - The script to read the code
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.GetCode
+    ///
+    /// Return an Error if something goes wrong
+    pub fn script_get_code(&self, script: &Script) -> Result<String, ShellyError> {
+        let mut code = String::new();
 
-            let mut checked_msg = None;
-            if msg.is_ok() {
-                checked_msg = Some(msg.unwrap())
-            }
+        loop {
+            let request = CodeRequest {
+                id: script.id,
+                offset: code.len(),
+            };
 
-            return Err(Box::new(InternalServerError {
-                code: id,
-                msg: checked_msg,
-            }));
+            let part: CodeResponse = self.call("Script.GetCode", &request)?;
+            debug!("{} : {} bytes read, {} bytes left", script.name, part.data.len(), part.left);
+            code.push_str(&part.data);
+
+            if part.left == 0 || part.data.is_empty() {
+                break;
+            }
         }
 
-        let body = response.text()?;
-        let data: serde_json::Value = serde_json::from_str(&body)?;
-        if let Some(simple_vec) = data["scripts"].as_array() {
-            let scripts: Vec<Script> = simple_vec
-                .iter()
-                .filter_map(|script| serde_json::from_value(script.clone()).ok())
-                .collect();
-            return Ok(scripts);
+        match code.strip_suffix(JS_STOP_FUNCTION) {
+            Some(stripped_code) => Ok(stripped_code.to_string()),
+            None => Ok(code),
         }
+    }
+
+    ///Will return a list of the currents `Vec<Script>` with their current status
+    ///
+    ///Return an Error if something goes wrong
+    pub fn script_list(&self) -> Result<Vec<Script>, ShellyError> {
+        let list: ScriptList = self.call("Script.List", &())?;
 
-        Err(Box::new(ParseScriptsInfo))
+        Ok(list.scripts)
     }
 
     ///Will start a script with the current name on the Shelly
@@ -277,44 +718,12 @@ impl Shelly {
     ///  * `script` - The [Script] to start
     ///
     ///Return an Error if something goes wrong
-    pub fn script_start(&self, script: &Script) -> Result<(), Box<dyn Error>> {
-        let autorun = std::env::var("shelly-autorun")?.parse::<bool>()?;
-        if script.running.unwrap() && !autorun {
+    pub fn script_start(&self, script: &Script) -> Result<(), ShellyError> {
+        if script.running == Some(true) && !self.autorun {
             return Ok(());
         }
 
-        let uri = "/rpc/Script.Start";
-        let url = self.get_url(uri);
-
-        let json = format!("{{ \"id\": \"{}\" }}", script.id);
-
-        let response = self.client
-            .post(&url)
-            .header("Content-Length", HeaderValue::from(json.len()))
-            .body(json)
-            .send_with_digest_auth(&self.username, &self.password)?;
-
-        if response.status().is_client_error() {
-            let error_code = response.status().as_u16();
-            let body = response.text()?;
-            error!("{}", &body);
-
-            return Err(Box::new(ClientRequestError { code: error_code }));
-        }
-        else if response.status().is_server_error() {
-            let id = response.status().as_u16().clone();
-            let msg = response.text();
-
-            let mut checked_msg = None;
-            if msg.is_ok() {
-                checked_msg = Some(msg.unwrap())
-            }
-
-            return Err(Box::new(InternalServerError {
-                code: id,
-                msg: checked_msg,
-            }));
-        }
+        let _: IgnoredAny = self.call("Script.Start", &ScriptId { id: script.id })?;
 
         Ok(())
     }
@@ -324,44 +733,119 @@ impl Shelly {
     /// * `script` - the [Script] that will be stop
     ///
     ///Return an Error if something goes wrong
-    pub fn script_stop(&self, script: &Script) -> Result<(), Box<dyn Error>> {
-        if !script.running.unwrap() {
+    pub fn script_stop(&self, script: &Script) -> Result<(), ShellyError> {
+        if script.running == Some(false) {
             return Ok(());
         }
 
-        let uri = "/rpc/Script.Stop";
-        let url = self.get_url(uri);
+        let _: IgnoredAny = self.call("Script.Stop", &ScriptId { id: script.id })?;
+
+        Ok(())
+    }
 
-        let json = format!("{{ \"id\": \"{}\" }}", script.id);
+    ///Will enable or disable a script, an enabled script is run on the boot of the Shelly
+    ///
+    /// * `script` - the [Script] to configure
+    /// * `enable` - If [true] the script is enabled, if [false] it is disabled
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.SetConfig
+    ///
+    ///Return an Error if something goes wrong
+    pub fn script_set_enable(&self, script: &Script, enable: bool) -> Result<(), ShellyError> {
+        let request = ScriptConfigRequest {
+            id: script.id,
+            config: ScriptConfig { enable },
+        };
+        let _: IgnoredAny = self.call("Script.SetConfig", &request)?;
 
-        let response = self.client
-            .post(&url)
-            .header("Content-Length", HeaderValue::from(json.len()))
-            .body(json)
-            .send_with_digest_auth(&self.username, &self.password)?;
+        Ok(())
+    }
+
+    ///Will evaluate some code in a running script, the code can use the variables and functions of the script
+    ///
+    /// * `script` - the [Script] where the code is evaluated, it has to be running
+    /// * `code` - the JavaScript code to evaluate
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.Eval
+    ///
+    ///Return the result of the code converted to a string, or an Error if something goes wrong
+    pub fn script_eval(&self, script: &Script, code: &str) -> Result<String, ShellyError> {
+        let response: EvalResponse = self.call("Script.Eval", &EvalRequest { id: script.id, code })?;
 
-        if response.status().is_client_error() {
-            let error_code = response.status().as_u16();
-            let body = response.text()?;
-            error!("{}", &body);
+        Ok(response.result)
+    }
 
-            return Err(Box::new(ClientRequestError { code: error_code }));
+    ///Will return the status of a script, with the memory it uses and its last errors
+    ///
+    /// * `script` - the [Script] to check
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.GetStatus
+    ///
+    ///Return an Error if something goes wrong
+    pub fn script_status(&self, script: &Script) -> Result<ScriptStatus, ShellyError> {
+        self.call("Script.GetStatus", &ScriptId { id: script.id })
+    }
+
+    ///Will return the identity of the Shelly, like its model and its firmware
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Shelly.GetDeviceInfo, or http://{shelly_ip}/shelly on the Gen1 devices
+    ///
+    ///Return an Error if something goes wrong
+    pub fn device_info(&self) -> Result<DeviceInfo, ShellyError> {
+        if self.supports_scripting() {
+            return self.call("Shelly.GetDeviceInfo", &());
         }
-        else if response.status().is_server_error() {
-            let id = response.status().as_u16().clone();
-            let msg = response.text();
 
-            let mut checked_msg = None;
-            if msg.is_ok() {
-                checked_msg = Some(msg.unwrap())
-            }
+        let probe: DeviceProbe = self.get_gen1("/shelly")?;
+        let model = probe.model.unwrap_or_default();
+        let mac = probe.mac.unwrap_or_default();
+        let fw_id = probe.fw.unwrap_or_default();
+
+        Ok(DeviceInfo {
+            name: None,
+            id: format!("{}-{}", model.to_lowercase(), mac.to_lowercase()),
+            mac,
+            model: model.clone(),
+            generation: 1,
+            ver: fw_id.split_once('/').map_or(fw_id.as_str(), |(_, version)| version).to_string(),
+            fw_id,
+            app: model,
+            auth_en: probe.auth.unwrap_or(false),
+        })
+    }
 
-            return Err(Box::new(InternalServerError {
-                code: id,
-                msg: checked_msg,
-            }));
+    ///Will return the status of the system of the Shelly, like its uptime and its free memory
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Sys.GetStatus, or http://{shelly_ip}/status on the Gen1 devices
+    ///
+    ///Return an Error if something goes wrong
+    pub fn sys_status(&self) -> Result<SysStatus, ShellyError> {
+        if self.supports_scripting() {
+            return self.call("Sys.GetStatus", &());
         }
 
+        let status: Gen1Status = self.get_gen1("/status")?;
+
+        Ok(SysStatus {
+            uptime: status.uptime,
+            ram_size: status.ram_total,
+            ram_free: status.ram_free,
+            fs_size: status.fs_size,
+            fs_free: status.fs_free,
+            restart_required: false,
+        })
+    }
+
+    ///Will delete a script with the current name on the Shelly
+    ///
+    /// * `script` - the [Script] that will be deleted
+    ///
+    ///Equivalent to http://{shelly_ip}/rpc/Script.Delete
+    ///
+    ///Return an Error if something goes wrong
+    pub fn script_delete(&self, script: &Script) -> Result<(), ShellyError> {
+        let _: IgnoredAny = self.call("Script.Delete", &ScriptId { id: script.id })?;
+
         Ok(())
     }
 }
@@ -371,50 +855,283 @@ impl Shelly {
 
 
 // Error thing
+
+///The errors that can happen when communicating with the Shelly
 #[derive(Debug)]
-struct ParseError;
-impl Error for ParseError {}
-impl Display for ParseError {
+pub enum ShellyError {
+    ///The Shelly refused the username or the password (response code 401)
+    Unauthorized,
+    ///The Shelly doesn't know the requested method or resource (response code 404)
+    NotFound(String),
+    ///There is no script with this name on the Shelly
+    ScriptNotFound(String),
+    ///The Shelly is too old for the scripting, it is only available on the Gen2 devices and newer
+    UnsupportedGeneration(u8),
+    ///The Shelly requires a password and none has been found
+    MissingPassword(String),
+    ///The name doesn't follow the naming rules of the scripts, checked before sending it to the Shelly
+    InvalidScriptName {
+        ///The name of the script
+        name: String,
+        ///The rule broken by the name
+        reason: String,
+    },
+    ///The Shelly returned an error for the requested method
+    Rpc {
+        ///The error code returned by the Shelly
+        code: i64,
+        ///The error message returned by the Shelly
+        message: String,
+    },
+    ///The request can't be sent to the Shelly
+    Transport(String),
+    ///The Shelly didn't answer in time
+    Timeout,
+    ///The answer of the Shelly can't be parsed
+    Parse(String),
+}
+
+impl Error for ShellyError {}
+
+impl Display for ShellyError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "failed to parse the id of the file, please provide it in the first line of the file. E.G '//3'")
+        match self {
+            ShellyError::Unauthorized => write!(f, "The Shelly refused the username or the password"),
+            ShellyError::NotFound(what) => write!(f, "Not found on the Shelly -> {}", what),
+            ShellyError::ScriptNotFound(name) => write!(f, "The script {} doesn't exist on the Shelly", name),
+            ShellyError::UnsupportedGeneration(generation) => write!(f, "The Shelly is a Gen{} device, the scripting is not supported on Gen{}, only on Gen2 devices and newer", generation, generation),
+            ShellyError::MissingPassword(error) => write!(f, "The Shelly requires a password -> {}", error),
+            ShellyError::InvalidScriptName { name, reason } => write!(f, "The script name {} is not valid, {}", name, reason),
+            ShellyError::Rpc { code, message } => write!(f, "The Shelly returned an error -> code {} : {}", code, message),
+            ShellyError::Transport(error) => write!(f, "Unable to communicate with the Shelly -> {}", error),
+            ShellyError::Timeout => write!(f, "The Shelly didn't answer in time"),
+            ShellyError::Parse(error) => write!(f, "Unable to parse the answer of the Shelly -> {}", error),
+        }
     }
 }
 
-#[derive(Debug)]
-struct InternalServerError {
-    code: u16,
-    msg: Option<String>,
+impl From<reqwest::Error> for ShellyError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_timeout() {
+            ShellyError::Timeout
+        } else {
+            ShellyError::Transport(error.to_string())
+        }
+    }
 }
-impl Error for InternalServerError {}
-impl Display for InternalServerError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut msg = format!("There is something wrong going on with the shelly -> response code {}", self.code);
-        if self.msg.is_some() {
-            msg = format!("{}\nError message -> {}", msg, self.msg.clone().unwrap());
+
+impl From<diqwest::error::Error> for ShellyError {
+    fn from(error: diqwest::error::Error) -> Self {
+        match error {
+            diqwest::error::Error::Reqwest(error) => error.into(),
+            other => ShellyError::Transport(other.to_string()),
         }
-        write!(f, "{msg}")
     }
 }
 
-#[derive(Debug)]
-struct ClientRequestError {
-    code: u16,
+impl From<serde_json::Error> for ShellyError {
+    fn from(error: serde_json::Error) -> Self {
+        ShellyError::Parse(error.to_string())
+    }
 }
-impl Error for ClientRequestError {}
-impl Display for ClientRequestError {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let msg = format!("Something goes wrong when trying to do the request to the shelly -> response code {}", self.code);
-        write!(f, "{msg}")
+
+///The error returned in the body of the answer by the Shelly
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+///Check the response code of the answer of the Shelly
+///
+/// * `response` - The answer of the Shelly
+///
+///Return the answer if the request succeeded, or the [ShellyError] matching the response code
+fn check_response(response: Response) -> Result<Response, ShellyError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
     }
+
+    let body = response.text().unwrap_or_default();
+    debug!("response code {} -> {}", status.as_u16(), body);
+
+    Err(status_error(status, body))
 }
 
-#[derive(Debug)]
-struct ParseScriptsInfo;
-impl Error for ParseScriptsInfo {}
-impl Display for ParseScriptsInfo {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Unable to parse correctly the list of scripts from the Shelly")
+///Get the [ShellyError] matching the response code of a failed request
+///
+/// * `status` - The response code of the answer
+/// * `body` - The body of the answer, it can contain the error returned by the Shelly
+fn status_error(status: StatusCode, body: String) -> ShellyError {
+    match status {
+        StatusCode::UNAUTHORIZED => ShellyError::Unauthorized,
+        StatusCode::NOT_FOUND => ShellyError::NotFound(body),
+        _ => {
+            let error = serde_json::from_str::<RpcResponse>(&body)
+                .ok()
+                .and_then(|response| response.error)
+                .or_else(|| serde_json::from_str::<RpcError>(&body).ok());
+
+            match error {
+                Some(error) => ShellyError::Rpc {
+                    code: error.code,
+                    message: error.message,
+                },
+                None => ShellyError::Rpc {
+                    code: status.as_u16() as i64,
+                    message: body,
+                },
+            }
+        }
     }
 }
 
+///Read the result of a JSON-RPC answer of the Shelly
+///
+/// * `body` - The body of the answer
+///
+///Return [ShellyError::Rpc] if the answer contains an error, the Shelly can return it with the response code 200
+fn decode_result<R: DeserializeOwned>(body: &str) -> Result<R, ShellyError> {
+    let response: RpcResponse = serde_json::from_str(body)?;
+    if let Some(error) = response.error {
+        return Err(ShellyError::Rpc {
+            code: error.code,
+            message: error.message,
+        });
+    }
+
+    Ok(serde_json::from_value(response.result.unwrap_or_default())?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_in_chunks_keeps_small_code_in_one_chunk() {
+        assert_eq!(split_in_chunks("let a = 1;", 1024), vec!["let a = 1;"]);
+    }
+
+    #[test]
+    fn split_in_chunks_sends_one_empty_chunk_for_empty_code() {
+        //the first chunk replaces the code, so an empty file must still send one chunk
+        assert_eq!(split_in_chunks("", 1024), vec![""]);
+    }
+
+    #[test]
+    fn split_in_chunks_splits_on_the_chunk_size() {
+        assert_eq!(split_in_chunks("abcdef", 2), vec!["ab", "cd", "ef"]);
+        assert_eq!(split_in_chunks("abcdefg", 3), vec!["abc", "def", "g"]);
+    }
 
+    #[test]
+    fn split_in_chunks_uses_at_least_one_byte() {
+        assert_eq!(split_in_chunks("abc", 0), vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn split_in_chunks_never_cuts_a_character() {
+        //'é' is 2 bytes, the chunk is extended to the end of the character
+        assert_eq!(split_in_chunks("aéb", 2), vec!["aé", "b"]);
+
+        let code = "const s = '🚀 héllo wörld';";
+        for chunk_size in 1..code.len() {
+            assert_eq!(split_in_chunks(code, chunk_size).concat(), code);
+        }
+    }
+
+    #[test]
+    fn decode_result_reads_the_result() {
+        let created: ScriptId = decode_result(r#"{"id":1,"src":"shelly","result":{"id":3}}"#).unwrap();
+        assert_eq!(created.id, 3);
+    }
+
+    #[test]
+    fn decode_result_returns_the_error_of_a_200_answer() {
+        let result = decode_result::<ScriptId>(r#"{"id":1,"src":"shelly","error":{"code":-105,"message":"Argument 'id', value 9 not found!"}}"#);
+
+        assert!(matches!(result, Err(ShellyError::Rpc { code: -105, ref message }) if message == "Argument 'id', value 9 not found!"));
+    }
+
+    #[test]
+    fn decode_result_fails_on_a_broken_answer() {
+        assert!(matches!(decode_result::<ScriptId>("not json"), Err(ShellyError::Parse(_))));
+    }
+
+    #[test]
+    fn status_error_reads_the_error_of_a_500_answer() {
+        let error = status_error(StatusCode::INTERNAL_SERVER_ERROR, r#"{"id":1,"src":"shelly","error":{"code":-103,"message":"Missing required argument 'id'!"}}"#.to_string());
+        assert!(matches!(error, ShellyError::Rpc { code: -103, ref message } if message == "Missing required argument 'id'!"));
+
+        let error = status_error(StatusCode::INTERNAL_SERVER_ERROR, r#"{"code":-114,"message":"Method not supported"}"#.to_string());
+        assert!(matches!(error, ShellyError::Rpc { code: -114, .. }));
+    }
+
+    #[test]
+    fn status_error_uses_the_response_code_without_body() {
+        let error = status_error(StatusCode::INTERNAL_SERVER_ERROR, String::new());
+
+        assert!(matches!(error, ShellyError::Rpc { code: 500, ref message } if message.is_empty()));
+    }
+
+    #[test]
+    fn status_error_maps_401_and_404() {
+        assert!(matches!(status_error(StatusCode::UNAUTHORIZED, String::new()), ShellyError::Unauthorized));
+        assert!(matches!(status_error(StatusCode::NOT_FOUND, String::from("No handler")), ShellyError::NotFound(ref body) if body == "No handler"));
+    }
+
+    fn invalid_reason(script_name: &str) -> Option<String> {
+        match validate_script_name(script_name) {
+            Ok(_) => None,
+            Err(ShellyError::InvalidScriptName { reason, .. }) => Some(reason),
+            Err(error) => panic!("unexpected error {}", error),
+        }
+    }
+
+    #[test]
+    fn validate_script_name_accepts_the_names_of_the_files() {
+        for script_name in ["motion", "lights_motion", "lights motion", "motion.v2", "motiön", "a:b", &"a".repeat(200)] {
+            assert_eq!(invalid_reason(script_name), None, "{}", script_name);
+        }
+    }
+
+    #[test]
+    fn validate_script_name_refuses_an_empty_name() {
+        assert_eq!(invalid_reason(""), Some("the name is empty".to_string()));
+    }
+
+    #[test]
+    fn validate_script_name_refuses_the_control_characters() {
+        for script_name in ["lights\nmotion", "motion\t", "\u{1b}[31mmotion"] {
+            assert!(invalid_reason(script_name).is_some_and(|reason| reason.contains("control character")), "{:?}", script_name);
+        }
+    }
+
+    #[test]
+    fn device_probe_detects_the_generation_and_the_authentication() {
+        //(answer of /shelly, generation, authentication enabled)
+        let cases = [
+            (r#"{"id":"shellyplus1-a8032ab1e2f0","mac":"A8032AB1E2F0","gen":2,"fw_id":"20231107-162425/1.0.8","auth_en":false}"#, 2, false),
+            (r#"{"id":"shellypro4pm-c8f09e8a1b2c","gen":3,"auth_en":true}"#, 3, true),
+            (r#"{"type":"SHSW-1","mac":"A4CF12F3B2C1","auth":true,"fw":"20230913-112003/v1.14.0-gcb84623"}"#, 1, true),
+            (r#"{"type":"SHSW-25","auth":false}"#, 1, false),
+            (r#"{}"#, FIRST_RPC_GENERATION, true),
+        ];
+
+        for (body, generation, auth_enabled) in cases {
+            let probe: DeviceProbe = serde_json::from_str(body).unwrap();
+
+            assert_eq!(probe.generation(), generation, "{}", body);
+            assert_eq!(probe.auth_enabled(), auth_enabled, "{}", body);
+        }
+    }
+
+    #[test]
+    fn device_probe_defaults_to_a_gen2_device_with_authentication() {
+        let probe = DeviceProbe::default();
+
+        assert_eq!(probe.generation(), FIRST_RPC_GENERATION);
+        assert!(probe.auth_enabled());
+    }
+}
@@ -2,6 +2,7 @@ use std::error::Error;
 use std::fs::File;
 use std::io::Write;
 use serde::{Deserialize, Serialize};
+use crate::config::{Config, DEFAULT_SCRIPTS_DIR};
 
 ///The vscode configuration
 #[derive(Debug)]
@@ -10,76 +11,100 @@ pub struct SetupVsCode {
 }
 
 impl SetupVsCode {
-    pub fn new() -> Result<Self, Box<dyn Error>>{
-        let host = std::env::var("shelly-host")?;
-        let username = std::env::var("shelly-username")?;
-        let password = std::env::var("shelly-password")?;
+    ///Create the vscode configuration
+    ///
+    /// The password is not written in the tasks, the utilitary will read it from the
+    /// environment variable, the credential file or a prompt when the task is run
+    ///
+    /// * `config` - The [Config] used by the tasks to communicate with the Shelly
+    pub fn new(config: &Config) -> Self {
+        let command = Self::command(config);
+        //without scripts_dir in the profile, the debugger checks the folder opened in vscode
+        let debug_path = if config.scripts_dir == DEFAULT_SCRIPTS_DIR { " --path ${workspaceFolder}" } else { "" };
 
-        Ok(
-            SetupVsCode {
-                tasks: Tasks {
-                    version: String::from("2.0.0"),
-                    tasks: vec![
-                        Task {
-                            label: String::from("Shelly Remote Helper | start current Script"),
-                            _type: String::from("shell"),
-                            command: format!("${{workspaceFolder}}/.vscode/Shelly_Remote_Helper.exe --host {host} --password {password} --username {username} start ${{fileBasenameNoExtension}}"),
-                            group: String::from("none"),
-                            presentation: Presentation {
-                                reveal: String::from("always"),
-                                panel: String::from("new"),
-                            },
-                            run_options: None,
-                            problem_matcher: Some(vec![]),
+        SetupVsCode {
+            tasks: Tasks {
+                version: String::from("2.0.0"),
+                tasks: vec![
+                    Task {
+                        label: String::from("Shelly Remote Helper | start current Script"),
+                        _type: String::from("shell"),
+                        command: format!("{command} start ${{fileBasenameNoExtension}}"),
+                        group: String::from("none"),
+                        presentation: Presentation {
+                            reveal: String::from("always"),
+                            panel: String::from("new"),
                         },
-                        Task {
-                            label: String::from("Shelly Remote Helper | stop current Script"),
-                            _type: String::from("shell"),
-                            command: format!("${{workspaceFolder}}/.vscode/Shelly_Remote_Helper.exe --host {host} --password {password} --username {username} stop ${{fileBasenameNoExtension}}"),
-                            group: String::from("none"),
-                            presentation: Presentation {
-                                reveal: String::from("always"),
-                                panel: String::from("new"),
-                            },
-                            run_options: None,
-                            problem_matcher: Some(vec![]),
+                        run_options: None,
+                        problem_matcher: Some(vec![]),
+                    },
+                    Task {
+                        label: String::from("Shelly Remote Helper | stop current Script"),
+                        _type: String::from("shell"),
+                        command: format!("{command} stop ${{fileBasenameNoExtension}}"),
+                        group: String::from("none"),
+                        presentation: Presentation {
+                            reveal: String::from("always"),
+                            panel: String::from("new"),
                         },
-                        Task {
-                            label: String::from("Shelly Remote Helper | list available script"),
-                            _type: String::from("shell"),
-                            command: format!("${{workspaceFolder}}/.vscode/Shelly_Remote_Helper.exe --host {host} --password {password} --username {username} list"),
-                            group: String::from("none"),
-                            presentation: Presentation {
-                                reveal: String::from("always"),
-                                panel: String::from("new"),
-                            },
-                            run_options: None,
-                            problem_matcher: Some(vec![]),
+                        run_options: None,
+                        problem_matcher: Some(vec![]),
+                    },
+                    Task {
+                        label: String::from("Shelly Remote Helper | list available script"),
+                        _type: String::from("shell"),
+                        command: format!("{command} list"),
+                        group: String::from("none"),
+                        presentation: Presentation {
+                            reveal: String::from("always"),
+                            panel: String::from("new"),
                         },
-                        Task {
-                            label: String::from("Shelly Remote Helper | start Debug"),
-                            _type: String::from("shell"),
-                            command: format!("${{workspaceFolder}}/.vscode/Shelly_Remote_Helper.exe --host {host} --password {password} --username {username} debug --path ${{workspaceFolder}}"),
-                            group: String::from("none"),
-                            presentation: Presentation {
-                                reveal: String::from("always"),
-                                panel: String::from("new"),
-                            },
-                            run_options: Some(RunOptions {
-                                run_on: String::from("folderOpen"),
-                            }),
-                            problem_matcher: None,
+                        run_options: None,
+                        problem_matcher: Some(vec![]),
+                    },
+                    Task {
+                        label: String::from("Shelly Remote Helper | start Debug"),
+                        _type: String::from("shell"),
+                        command: format!("{command} debug{debug_path}"),
+                        group: String::from("none"),
+                        presentation: Presentation {
+                            reveal: String::from("always"),
+                            panel: String::from("new"),
                         },
-                    ],
-                },
-            }
-        )
+                        run_options: Some(RunOptions {
+                            run_on: String::from("folderOpen"),
+                        }),
+                        problem_matcher: None,
+                    },
+                ],
+            },
+        }
+    }
+
+    ///Get the command running the utilitary with the options to communicate with the Shelly
+    ///
+    /// With a profile only its name is indicated, like this the tasks follow the changes of the config file
+    ///
+    /// * `config` - The [Config] used by the tasks to communicate with the Shelly
+    fn command(config: &Config) -> String {
+        let mut command = String::from("${workspaceFolder}/.vscode/Shelly_Remote_Helper.exe");
+
+        if let Some(config_file) = &config.config_file {
+            command.push_str(&format!(" --config {config_file}"));
+        }
+
+        match &config.profile {
+            Some(profile) => command.push_str(&format!(" --profile {profile}")),
+            None => command.push_str(&format!(" --host {} --port {} --username {}", config.host, config.port, config.username)),
+        }
+
+        command
     }
 
     pub fn write(&self) -> Result<(), Box<dyn Error>> {
         let json_tasks = serde_json::to_string_pretty(&self.tasks)?;
         let mut file = File::create("./tasks.json")?;
-        file.write(json_tasks.as_bytes())?;
+        file.write_all(json_tasks.as_bytes())?;
 
         Ok(())
     }